@@ -0,0 +1,395 @@
+//! In-place reader over a [`crate::container`] file, e.g. a memory-mapped one.
+//!
+//! Parsing only checks the section directory and the string and blob tables; the
+//! checksums and the content hash are checked by [`AssemblyView::verify`]. Names are
+//! borrowed from the input, records are read when asked for, and a method body is
+//! decoded the first time it is requested and cached afterwards.
+
+use crate::attrs::{AttrLayout, ConstValue, CustomAttribute, FieldAttr, MethodAttr, TypeAttr};
+use crate::container::{
+    ContentHash, Directory, FIELD_RECORD_LEN, METHOD_RECORD_LEN, Records, SectionKind,
+    TYPE_RECORD_LEN, Table, decode_manifest,
+};
+use crate::errors::{BinaryError, GenericError};
+use crate::generics::GenericParamDef;
+use crate::instruction::StringInstruction;
+use crate::io_utils::BinaryReader;
+use crate::manifest::{AssemblyIdentity, Manifest};
+use crate::metadata::{AssemblyDef, FieldDef, MethodDef, MethodSignature, TypeDef};
+use crate::{StringName, StringTypeReference};
+use semver::Version;
+use std::sync::OnceLock;
+
+pub struct AssemblyView<'a> {
+    directory: Directory<'a>,
+    name: &'a str,
+    manifest: Manifest,
+    strings: Table<'a>,
+    blobs: Table<'a>,
+    types: Records<'a>,
+    fields: Records<'a>,
+    methods: Records<'a>,
+    code: &'a [u8],
+    bodies: Box<[OnceLock<Vec<StringInstruction>>]>,
+}
+
+impl<'a> AssemblyView<'a> {
+    #[track_caller]
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, GenericError<BinaryError>> {
+        Self::new(Directory::parse(bytes)?)
+    }
+    #[track_caller]
+    pub(crate) fn new(directory: Directory<'a>) -> Result<Self, GenericError<BinaryError>> {
+        let strings = Table::parse(directory.section(SectionKind::Strings)?)?;
+        let methods = Records::parse(directory.section(SectionKind::Methods)?, METHOD_RECORD_LEN)?;
+        let name = strings
+            .str(BinaryReader::new(directory.section(SectionKind::Assembly)?).read_u32()?)?;
+        let (version, references) = match directory.optional_section(SectionKind::Manifest)? {
+            Some(bytes) => decode_manifest(bytes)?,
+            None => (Version::new(0, 0, 0), Vec::new()),
+        };
+        let identity = AssemblyIdentity::new(StringName::from(name), version);
+        let identity = match directory.stored_hash() {
+            Some(hash) => identity.with_content_hash(hash),
+            None => identity,
+        };
+        Ok(Self {
+            manifest: Manifest::new(identity, references),
+            name,
+            blobs: Table::parse(directory.section(SectionKind::Blobs)?)?,
+            types: Records::parse(directory.section(SectionKind::Types)?, TYPE_RECORD_LEN)?,
+            fields: Records::parse(directory.section(SectionKind::Fields)?, FIELD_RECORD_LEN)?,
+            bodies: (0..methods.len()).map(|_| OnceLock::new()).collect(),
+            code: directory.section(SectionKind::Code)?,
+            strings,
+            methods,
+            directory,
+        })
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+    /// As stored in the header, `None` for version 1 files; see [`Self::verify`].
+    pub fn content_hash(&self) -> Option<ContentHash> {
+        *self.manifest.identity().content_hash()
+    }
+    /// Checks the section checksums and the stored content hash, and returns the hash of
+    /// the contents.
+    #[track_caller]
+    pub fn verify(&self) -> Result<ContentHash, GenericError<BinaryError>> {
+        self.directory.verify()
+    }
+    /// Identity, including the content hash, and referenced assemblies.
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+    pub fn type_count(&self) -> usize {
+        self.types.len()
+    }
+    pub fn method_count(&self) -> usize {
+        self.methods.len()
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = TypeView<'_, 'a>> {
+        (0..self.types.len() as u32).map(|i| self.type_at(i).unwrap())
+    }
+    #[track_caller]
+    pub fn type_at(&self, index: u32) -> Result<TypeView<'_, 'a>, GenericError<BinaryError>> {
+        Ok(TypeView {
+            view: self,
+            record: self.types.get(index)?,
+        })
+    }
+    /// Compares raw bytes, so it neither allocates nor fails on malformed names.
+    pub fn find_type(&self, name: &str) -> Option<TypeView<'_, 'a>> {
+        self.types()
+            .find(|x| self.strings.get(x.slot(0)) == Some(name.as_bytes()))
+    }
+    #[track_caller]
+    pub fn method_at(&self, index: u32) -> Result<MethodView<'_, 'a>, GenericError<BinaryError>> {
+        Ok(MethodView {
+            view: self,
+            index,
+            record: self.methods.get(index)?,
+        })
+    }
+
+    /// Decodes everything into owned definitions, without validating them.
+    #[track_caller]
+    pub fn to_assembly_def(&self) -> Result<AssemblyDef, GenericError<BinaryError>> {
+        Ok(AssemblyDef::builder()
+            .name(self.name)
+            .version(self.manifest.identity().version().clone())
+            .references(self.manifest.references().clone())
+            .types(self.types().map(|x| x.to_type_def()).try_collect()?)
+            .build())
+    }
+}
+
+/// Reads the `u32` at `slot` of a record: slot 0 is the name, the following ones come
+/// after the `attr_len` bytes of the attribute header.
+fn record_u32(record: &[u8], attr_len: usize, slot: usize) -> u32 {
+    let at = if slot == 0 { 0 } else { attr_len + slot * 4 };
+    u32::from_le_bytes(record[at..at + 4].try_into().unwrap())
+}
+
+#[derive(Clone, Copy)]
+pub struct TypeView<'v, 'a> {
+    view: &'v AssemblyView<'a>,
+    record: &'a [u8],
+}
+
+impl<'v, 'a> TypeView<'v, 'a> {
+    fn slot(&self, slot: usize) -> u32 {
+        record_u32(self.record, TypeAttr::ENCODED_LEN, slot)
+    }
+    #[track_caller]
+    pub fn name(&self) -> Result<&'a str, GenericError<BinaryError>> {
+        self.view.strings.str(self.slot(0))
+    }
+    #[track_caller]
+    pub fn attr(&self) -> Result<TypeAttr, GenericError<BinaryError>> {
+        TypeAttr::decode(&self.record[4..])
+    }
+    #[track_caller]
+    pub fn custom_attrs(&self) -> Result<Vec<CustomAttribute>, GenericError<BinaryError>> {
+        Ok(self.extra()?.0)
+    }
+    #[track_caller]
+    pub fn generic_params(&self) -> Result<Vec<GenericParamDef>, GenericError<BinaryError>> {
+        Ok(self.extra()?.1)
+    }
+    #[track_caller]
+    fn extra(
+        &self,
+    ) -> Result<(Vec<CustomAttribute>, Vec<GenericParamDef>), GenericError<BinaryError>> {
+        self.view.blobs.blob(self.slot(3))
+    }
+    #[track_caller]
+    pub fn parent(&self) -> Result<Option<StringTypeReference>, GenericError<BinaryError>> {
+        self.view.blobs.blob(self.slot(1))
+    }
+    #[track_caller]
+    pub fn interfaces(&self) -> Result<Vec<StringTypeReference>, GenericError<BinaryError>> {
+        self.view.blobs.blob(self.slot(2))
+    }
+    #[track_caller]
+    pub fn fields(
+        &self,
+    ) -> Result<impl Iterator<Item = FieldView<'v, 'a>> + use<'v, 'a>, GenericError<BinaryError>>
+    {
+        let view = self.view;
+        Ok(view
+            .fields
+            .range(self.slot(4), self.slot(5))?
+            .map(move |i| FieldView {
+                view,
+                record: view.fields.get(i).unwrap(),
+            }))
+    }
+    #[track_caller]
+    pub fn methods(
+        &self,
+    ) -> Result<impl Iterator<Item = MethodView<'v, 'a>> + use<'v, 'a>, GenericError<BinaryError>>
+    {
+        let view = self.view;
+        Ok(view
+            .methods
+            .range(self.slot(6), self.slot(7))?
+            .map(move |i| view.method_at(i).unwrap()))
+    }
+    #[track_caller]
+    pub fn find_method(
+        &self,
+        name: &str,
+    ) -> Result<Option<MethodView<'v, 'a>>, GenericError<BinaryError>> {
+        Ok(self
+            .methods()?
+            .find(|x| self.view.strings.get(x.slot(0)) == Some(name.as_bytes())))
+    }
+    #[track_caller]
+    pub fn to_type_def(&self) -> Result<TypeDef, GenericError<BinaryError>> {
+        Ok(TypeDef::builder()
+            .name(self.name()?)
+            .attr(self.attr()?)
+            .maybe_parent(self.parent()?)
+            .interfaces(self.interfaces()?)
+            .fields(self.fields()?.map(|x| x.to_field_def()).try_collect()?)
+            .methods(self.methods()?.map(|x| x.to_method_def()).try_collect()?)
+            .custom_attrs(self.custom_attrs()?)
+            .generic_params(self.generic_params()?)
+            .build())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FieldView<'v, 'a> {
+    view: &'v AssemblyView<'a>,
+    record: &'a [u8],
+}
+
+impl<'a> FieldView<'_, 'a> {
+    fn slot(&self, slot: usize) -> u32 {
+        record_u32(self.record, FieldAttr::ENCODED_LEN, slot)
+    }
+    #[track_caller]
+    pub fn name(&self) -> Result<&'a str, GenericError<BinaryError>> {
+        self.view.strings.str(self.slot(0))
+    }
+    #[track_caller]
+    pub fn attr(&self) -> Result<FieldAttr, GenericError<BinaryError>> {
+        FieldAttr::decode(&self.record[4..])
+    }
+    #[track_caller]
+    pub fn custom_attrs(&self) -> Result<Vec<CustomAttribute>, GenericError<BinaryError>> {
+        Ok(self.extra()?.1)
+    }
+    #[track_caller]
+    pub fn const_value(&self) -> Result<Option<ConstValue>, GenericError<BinaryError>> {
+        Ok(self.extra()?.0)
+    }
+    #[track_caller]
+    fn extra(
+        &self,
+    ) -> Result<(Option<ConstValue>, Vec<CustomAttribute>), GenericError<BinaryError>> {
+        self.view.blobs.blob(self.slot(2))
+    }
+    #[track_caller]
+    pub fn ty(&self) -> Result<StringTypeReference, GenericError<BinaryError>> {
+        self.view.blobs.blob(self.slot(1))
+    }
+    #[track_caller]
+    pub fn to_field_def(&self) -> Result<FieldDef, GenericError<BinaryError>> {
+        Ok(FieldDef::builder()
+            .name(self.name()?)
+            .attr(self.attr()?)
+            .ty(self.ty()?)
+            .maybe_const_value(self.const_value()?)
+            .custom_attrs(self.custom_attrs()?)
+            .build())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct MethodView<'v, 'a> {
+    view: &'v AssemblyView<'a>,
+    index: u32,
+    record: &'a [u8],
+}
+
+impl<'v, 'a> MethodView<'v, 'a> {
+    fn slot(&self, slot: usize) -> u32 {
+        record_u32(self.record, MethodAttr::ENCODED_LEN, slot)
+    }
+    /// Index in the assembly's method table, usable with [`AssemblyView::method_at`].
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+    #[track_caller]
+    pub fn name(&self) -> Result<&'a str, GenericError<BinaryError>> {
+        self.view.strings.str(self.slot(0))
+    }
+    #[track_caller]
+    pub fn attr(&self) -> Result<MethodAttr, GenericError<BinaryError>> {
+        MethodAttr::decode(&self.record[4..])
+    }
+    #[track_caller]
+    pub fn custom_attrs(&self) -> Result<Vec<CustomAttribute>, GenericError<BinaryError>> {
+        Ok(self.extra()?.0)
+    }
+    #[track_caller]
+    pub fn generic_params(&self) -> Result<Vec<GenericParamDef>, GenericError<BinaryError>> {
+        Ok(self.extra()?.1)
+    }
+    #[track_caller]
+    fn extra(
+        &self,
+    ) -> Result<(Vec<CustomAttribute>, Vec<GenericParamDef>), GenericError<BinaryError>> {
+        self.view.blobs.blob(self.slot(2))
+    }
+    /// The borsh-encoded [`MethodSignature`].
+    #[track_caller]
+    pub fn signature_bytes(&self) -> Result<&'a [u8], GenericError<BinaryError>> {
+        self.view
+            .blobs
+            .get(self.slot(1))
+            .ok_or(BinaryError::IndexOutOfRange.throw())
+    }
+    #[track_caller]
+    pub fn signature(&self) -> Result<MethodSignature, GenericError<BinaryError>> {
+        self.view.blobs.blob(self.slot(1))
+    }
+    pub fn has_body(&self) -> bool {
+        self.slot(4) != 0
+    }
+    /// The borsh-encoded body, empty for methods without one.
+    #[track_caller]
+    pub fn code_bytes(&self) -> Result<&'a [u8], GenericError<BinaryError>> {
+        let (offset, len) = (self.slot(3) as usize, self.slot(4) as usize);
+        self.view
+            .code
+            .get(offset..offset + len)
+            .ok_or(BinaryError::IndexOutOfRange.throw())
+    }
+    /// Decodes the body on first call; later calls return the cached instructions.
+    #[track_caller]
+    pub fn body(&self) -> Result<&'v [StringInstruction], GenericError<BinaryError>> {
+        let cell = &self.view.bodies[self.index as usize];
+        if let Some(body) = cell.get() {
+            return Ok(body);
+        }
+        let body = match self.code_bytes()? {
+            [] => Vec::new(),
+            code => borsh::from_slice(code).map_err(|_| BinaryError::WrongFileFormat.throw())?,
+        };
+        Ok(cell.get_or_init(|| body))
+    }
+    pub fn is_body_decoded(&self) -> bool {
+        self.view.bodies[self.index as usize].get().is_some()
+    }
+    #[track_caller]
+    pub fn to_method_def(&self) -> Result<MethodDef, GenericError<BinaryError>> {
+        Ok(MethodDef::builder()
+            .name(StringName::from(self.name()?))
+            .attr(self.attr()?)
+            .signature(self.signature()?)
+            .body(self.body()?.to_vec())
+            .custom_attrs(self.custom_attrs()?)
+            .generic_params(self.generic_params()?)
+            .build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::encode_assembly;
+    use crate::fixtures;
+
+    #[test]
+    fn test_lazy_view() {
+        let assembly = fixtures::program(vec![StringInstruction::ReturnVal { register_addr: 0 }]);
+        let bytes = encode_assembly(&assembly);
+
+        let view = AssemblyView::parse(&bytes).unwrap();
+        let name = view.find_type("App.Program").unwrap().name().unwrap();
+        assert!(bytes.as_ptr_range().contains(&name.as_ptr()));
+        assert!(view.find_type("App.Missing").is_none());
+
+        let main = view
+            .find_type("App.Program")
+            .unwrap()
+            .find_method("Main()")
+            .unwrap()
+            .unwrap();
+        assert!(!main.is_body_decoded());
+        let body = main.body().unwrap();
+        assert!(main.is_body_decoded());
+        assert!(std::ptr::eq(
+            body,
+            view.method_at(0).unwrap().body().unwrap()
+        ));
+        assert_eq!(view.to_assembly_def().unwrap(), assembly);
+    }
+}
@@ -1,92 +1,653 @@
+use crate::errors::{AttrError, BinaryError, GenericError};
+use crate::string_reference::MAX_TYPE_DEPTH;
+use crate::{StringName, StringTypeReference};
+use borsh::{BorshDeserialize, BorshSerialize};
 use derive_ctor::ctor;
-use enumflags2::{BitFlags, bitflags};
-use getset::{CopyGetters, MutGetters, Setters};
+use enumflags2::{BitFlag, BitFlags, bitflags};
+use getset::{CopyGetters, Getters, MutGetters, Setters};
+use indexmap::IndexMap;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use proc_macros::{UnwrapEnum, WithType};
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
+/// Borsh encoding of `BitFlags` as their raw bits, rejecting unknown bits.
+pub(crate) mod borsh_flags {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use enumflags2::{BitFlag, BitFlags};
+    use std::fmt::Debug;
+    use std::io::{Error, ErrorKind, Read, Result, Write};
+
+    pub fn serialize<T, W>(flags: &BitFlags<T>, writer: &mut W) -> Result<()>
+    where
+        T: BitFlag,
+        T::Numeric: BorshSerialize,
+        W: Write,
+    {
+        flags.bits().serialize(writer)
+    }
+
+    pub fn deserialize<T, R>(reader: &mut R) -> Result<BitFlags<T>>
+    where
+        T: BitFlag + Debug,
+        T::Numeric: BorshDeserialize,
+        R: Read,
+    {
+        let bits = T::Numeric::deserialize_reader(reader)?;
+        BitFlags::from_bits(bits).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+/// Checks `flags` against `(a, b)` pairs that must not be set together and
+/// `(flag, required)` pairs.
+#[track_caller]
+pub(crate) fn check_flags<T: BitFlag + Debug>(
+    flags: BitFlags<T>,
+    conflicts: &[(T, T)],
+    requirements: &[(T, T)],
+) -> Result<(), GenericError<AttrError>> {
+    if let Some((a, b)) = conflicts
+        .iter()
+        .find(|(a, b)| flags.contains(*a) && flags.contains(*b))
+    {
+        return Err(AttrError::ConflictingFlags(format!("{a:?}"), format!("{b:?}")).throw());
+    }
+    if let Some((flag, required)) = requirements
+        .iter()
+        .find(|(flag, required)| flags.contains(*flag) && !flags.contains(*required))
+    {
+        return Err(AttrError::MissingFlag {
+            flag: format!("{required:?}"),
+            required_by: format!("{flag:?}"),
+        }
+        .throw());
+    }
+    Ok(())
+}
+
+macro impl_flag_predicates($attr:ty, $flags:ident { $($name:ident => $flag:ident),* $(,)? }) {
+    impl $attr {
+        $(
+            pub fn $name(&self) -> bool {
+                self.impl_flags.contains($flags::$flag)
+            }
+        )*
+    }
+}
+
 #[bitflags]
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum FieldImplementationFlags {
-    Static,
+    Static = 1 << 0,
+    ReadOnly = 1 << 1,
+    /// Compile-time constant; the value is [`crate::metadata::FieldDef::const_value`].
+    Const = 1 << 2,
+    Volatile = 1 << 3,
+    ThreadStatic = 1 << 4,
+    /// Excluded from serialization.
+    Transient = 1 << 5,
+}
+
+/// A literal value, e.g. of a `Const` field.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ConstValue {
+    Null,
+    Boolean(bool),
+    Char(char),
+    UInt8(u8),
+    Int8(i8),
+    UInt16(u16),
+    Int16(i16),
+    UInt32(u32),
+    Int32(i32),
+    UInt64(u64),
+    Int64(i64),
+    Float32(f32),
+    Float64(f64),
+    String(StringName),
+    /// A type literal, e.g. `typeof(T)`.
+    Type(StringTypeReference),
+    /// Element type and elements.
+    Array(StringTypeReference, Vec<ConstValue>),
+}
+
+impl ConstValue {
+    /// Type of the value, `None` for `Null`.
+    pub fn type_ref(&self) -> Option<StringTypeReference> {
+        use crate::core_types::CoreType;
+        Some(match self {
+            ConstValue::Null => return None,
+            ConstValue::Boolean(_) => bool::TYPE_REF,
+            ConstValue::Char(_) => char::TYPE_REF,
+            ConstValue::UInt8(_) => u8::TYPE_REF,
+            ConstValue::Int8(_) => i8::TYPE_REF,
+            ConstValue::UInt16(_) => u16::TYPE_REF,
+            ConstValue::Int16(_) => i16::TYPE_REF,
+            ConstValue::UInt32(_) => u32::TYPE_REF,
+            ConstValue::Int32(_) => i32::TYPE_REF,
+            ConstValue::UInt64(_) => u64::TYPE_REF,
+            ConstValue::Int64(_) => i64::TYPE_REF,
+            ConstValue::Float32(_) => f32::TYPE_REF,
+            ConstValue::Float64(_) => f64::TYPE_REF,
+            ConstValue::String(_) => StringName::TYPE_REF,
+            ConstValue::Type(_) => crate::core_types::TYPE,
+            ConstValue::Array(elem, _) => StringTypeReference::make_array(elem.clone()),
+        })
+    }
+}
+
+/// Floats are written as their bits since borsh rejects NaN, chars as `u32`.
+impl BorshSerialize for ConstValue {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        fn tagged<T: BorshSerialize, W: std::io::Write>(
+            tag: u8,
+            value: &T,
+            writer: &mut W,
+        ) -> std::io::Result<()> {
+            BorshSerialize::serialize(&tag, writer)?;
+            value.serialize(writer)
+        }
+        match self {
+            ConstValue::Null => tagged(0, &(), writer),
+            ConstValue::Boolean(x) => tagged(1, x, writer),
+            ConstValue::Char(x) => tagged(2, &(*x as u32), writer),
+            ConstValue::UInt8(x) => tagged(3, x, writer),
+            ConstValue::Int8(x) => tagged(4, x, writer),
+            ConstValue::UInt16(x) => tagged(5, x, writer),
+            ConstValue::Int16(x) => tagged(6, x, writer),
+            ConstValue::UInt32(x) => tagged(7, x, writer),
+            ConstValue::Int32(x) => tagged(8, x, writer),
+            ConstValue::UInt64(x) => tagged(9, x, writer),
+            ConstValue::Int64(x) => tagged(10, x, writer),
+            ConstValue::Float32(x) => tagged(11, &x.to_bits(), writer),
+            ConstValue::Float64(x) => tagged(12, &x.to_bits(), writer),
+            ConstValue::String(x) => tagged(13, x, writer),
+            ConstValue::Type(x) => tagged(14, x, writer),
+            ConstValue::Array(elem, values) => {
+                tagged(15, elem, writer)?;
+                BorshSerialize::serialize(values, writer)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for ConstValue {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        read_const_value(reader, 0)
+    }
+}
+
+/// Arrays of arrays nest like their array types, so they share [`MAX_TYPE_DEPTH`].
+fn read_const_value<R: std::io::Read>(reader: &mut R, depth: usize) -> std::io::Result<ConstValue> {
+    if depth > MAX_TYPE_DEPTH {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "ConstValue nested too deeply",
+        ));
+    }
+    let tag = u8::deserialize_reader(reader)?;
+    Ok(match tag {
+        0 => ConstValue::Null,
+        1 => ConstValue::Boolean(bool::deserialize_reader(reader)?),
+        2 => ConstValue::Char(
+            char::from_u32(u32::deserialize_reader(reader)?).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid char")
+            })?,
+        ),
+        3 => ConstValue::UInt8(u8::deserialize_reader(reader)?),
+        4 => ConstValue::Int8(i8::deserialize_reader(reader)?),
+        5 => ConstValue::UInt16(u16::deserialize_reader(reader)?),
+        6 => ConstValue::Int16(i16::deserialize_reader(reader)?),
+        7 => ConstValue::UInt32(u32::deserialize_reader(reader)?),
+        8 => ConstValue::Int32(i32::deserialize_reader(reader)?),
+        9 => ConstValue::UInt64(u64::deserialize_reader(reader)?),
+        10 => ConstValue::Int64(i64::deserialize_reader(reader)?),
+        11 => ConstValue::Float32(f32::from_bits(u32::deserialize_reader(reader)?)),
+        12 => ConstValue::Float64(f64::from_bits(u64::deserialize_reader(reader)?)),
+        13 => ConstValue::String(StringName::deserialize_reader(reader)?),
+        14 => ConstValue::Type(StringTypeReference::deserialize_reader(reader)?),
+        15 => ConstValue::Array(
+            StringTypeReference::deserialize_reader(reader)?,
+            read_const_values(reader, depth + 1)?,
+        ),
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unexpected tag {tag} for ConstValue"),
+            ));
+        }
+    })
+}
+
+/// Layout of a borsh `Vec`.
+fn read_const_values<R: std::io::Read>(
+    reader: &mut R,
+    depth: usize,
+) -> std::io::Result<Vec<ConstValue>> {
+    let len = u32::deserialize_reader(reader)?;
+    (0..len).map(|_| read_const_value(reader, depth)).collect()
+}
+
+/// User-defined metadata such as `@Deprecated("use X")`: the attribute type plus the
+/// arguments of its constructor call and its named arguments.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    ctor,
+    Getters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[ctor(pub new)]
+#[getset(get = "pub", get_mut = "pub")]
+pub struct CustomAttribute {
+    ty: StringTypeReference,
+    ctor_args: Vec<ConstValue>,
+    named_args: IndexMap<StringName, ConstValue>,
+}
+
+impl CustomAttribute {
+    pub fn named_arg(&self, name: &str) -> Option<&ConstValue> {
+        self.named_args.get(name)
+    }
+    /// Sorts the named arguments by name; their order carries no meaning.
+    pub fn canonicalize(&mut self) {
+        self.named_args.sort_keys();
+    }
 }
 
-#[derive(Clone, Copy, Debug, ctor, CopyGetters, Setters, MutGetters)]
+/// Members that can carry [`CustomAttribute`]s.
+pub trait HasCustomAttributes {
+    fn custom_attrs(&self) -> &[CustomAttribute];
+    fn custom_attrs_mut(&mut self) -> &mut Vec<CustomAttribute>;
+
+    fn find_custom_attr(&self, ty: &StringTypeReference) -> Option<&CustomAttribute> {
+        self.custom_attrs().iter().find(|x| x.ty() == ty)
+    }
+    fn has_custom_attr(&self, ty: &StringTypeReference) -> bool {
+        self.find_custom_attr(ty).is_some()
+    }
+    fn add_custom_attr(&mut self, attr: CustomAttribute) {
+        self.custom_attrs_mut().push(attr)
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    ctor,
+    CopyGetters,
+    Setters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
 #[ctor(pub new)]
 #[getset(set = "pub", get_mut = "pub")]
 #[get_copy = "pub"]
 pub struct FieldAttr {
     vis: Visibility,
+    #[borsh(
+        serialize_with = "borsh_flags::serialize",
+        deserialize_with = "borsh_flags::deserialize"
+    )]
     impl_flags: BitFlags<FieldImplementationFlags>,
 }
 
 impl FieldAttr {
-    pub fn is_static(&self) -> bool {
-        self.impl_flags.contains(FieldImplementationFlags::Static)
+    /// A `Static` `Const` field; its value goes on the [`crate::metadata::FieldDef`].
+    pub fn new_const(vis: Visibility) -> Self {
+        Self::new(
+            vis,
+            FieldImplementationFlags::Static | FieldImplementationFlags::Const,
+        )
+    }
+
+    /// Rejects contradictory flags. Whether a value is present is checked by
+    /// [`crate::metadata::FieldDef::validate`].
+    #[track_caller]
+    pub fn validate(&self) -> Result<(), GenericError<AttrError>> {
+        use FieldImplementationFlags::*;
+        check_flags(
+            self.impl_flags,
+            &[
+                (Const, ReadOnly),
+                (Const, Volatile),
+                (Const, ThreadStatic),
+                (ReadOnly, Volatile),
+            ],
+            &[(Const, Static), (ThreadStatic, Static)],
+        )
     }
 }
 
+impl_flag_predicates!(FieldAttr, FieldImplementationFlags {
+    is_static => Static,
+    is_readonly => ReadOnly,
+    is_const => Const,
+    is_volatile => Volatile,
+    is_thread_static => ThreadStatic,
+    is_transient => Transient,
+});
+
 #[bitflags]
-#[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u16)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum MethodImplementationFlags {
-    Static,
-    ImplementedByRuntime,
+    Static = 1 << 0,
+    ImplementedByRuntime = 1 << 1,
+    Virtual = 1 << 2,
+    Abstract = 1 << 3,
+    Override = 1 << 4,
+    Sealed = 1 << 5,
+    /// Always takes a new vtable slot instead of reusing the parent's.
+    NewSlot = 1 << 6,
+    Native = 1 << 7,
+    Constructor = 1 << 8,
+    Async = 1 << 9,
+    Generic = 1 << 10,
 }
 
-#[derive(Clone, Copy, CopyGetters, Debug, ctor, Setters, MutGetters)]
+#[derive(
+    Clone,
+    Copy,
+    CopyGetters,
+    Debug,
+    PartialEq,
+    ctor,
+    Setters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
 #[ctor(pub new)]
 #[getset(set = "pub", get_mut = "pub")]
 #[get_copy = "pub"]
 pub struct MethodAttr {
     vis: Visibility,
+    #[borsh(
+        serialize_with = "borsh_flags::serialize",
+        deserialize_with = "borsh_flags::deserialize"
+    )]
     impl_flags: BitFlags<MethodImplementationFlags>,
     register_len: u64,
 }
 
-#[derive(Clone, Copy, Debug, TryFromPrimitive, IntoPrimitive)]
+impl MethodAttr {
+    const FLAG_CONFLICTS: &[(MethodImplementationFlags, MethodImplementationFlags)] = {
+        use MethodImplementationFlags::*;
+        &[
+            (Static, Virtual),
+            (Static, Abstract),
+            (Static, Override),
+            (Static, Sealed),
+            (Static, NewSlot),
+            (Abstract, Sealed),
+            (Abstract, Native),
+            (Abstract, ImplementedByRuntime),
+            (Native, ImplementedByRuntime),
+            (Override, NewSlot),
+            (Constructor, Virtual),
+            (Constructor, Async),
+            (Constructor, Generic),
+        ]
+    };
+    /// `(flag, required)` pairs.
+    const FLAG_REQUIREMENTS: &[(MethodImplementationFlags, MethodImplementationFlags)] = {
+        use MethodImplementationFlags::*;
+        &[
+            (Abstract, Virtual),
+            (Override, Virtual),
+            (NewSlot, Virtual),
+            (Sealed, Override),
+        ]
+    };
+
+    /// Rejects contradictory flags, and bodies on abstract, native or runtime-implemented
+    /// methods (or their absence on any other method).
+    #[track_caller]
+    pub fn validate(&self, has_body: bool) -> Result<(), GenericError<AttrError>> {
+        check_flags(
+            self.impl_flags,
+            Self::FLAG_CONFLICTS,
+            Self::FLAG_REQUIREMENTS,
+        )?;
+        let bodiless = self.impl_flags
+            & (MethodImplementationFlags::Abstract
+                | MethodImplementationFlags::Native
+                | MethodImplementationFlags::ImplementedByRuntime);
+        match (bodiless.iter().next(), has_body) {
+            (Some(flag), true) => Err(AttrError::UnexpectedBody(format!("{flag:?}")).throw()),
+            (None, false) => Err(AttrError::MissingBody.throw()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl_flag_predicates!(MethodAttr, MethodImplementationFlags {
+    is_static => Static,
+    is_implemented_by_runtime => ImplementedByRuntime,
+    is_virtual => Virtual,
+    is_abstract => Abstract,
+    is_override => Override,
+    is_sealed => Sealed,
+    is_new_slot => NewSlot,
+    is_native => Native,
+    is_constructor => Constructor,
+    is_async => Async,
+    is_generic => Generic,
+});
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    TryFromPrimitive,
+    IntoPrimitive,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[borsh(use_discriminant = true)]
 #[repr(u8)]
 pub enum Visibility {
-    Public,
-    Private,
-    AssemblyOnly,
+    Public = 0,
+    Private = 1,
+    AssemblyOnly = 2,
+    /// Declaring type, its nested types and subclasses.
+    Protected = 3,
+    /// Either `Protected` or `AssemblyOnly` grants access.
+    ProtectedOrAssembly = 4,
+    /// Both `Protected` and `AssemblyOnly` must grant access.
+    ProtectedAndAssembly = 5,
+}
+
+/// Inheritance information supplied by whoever owns the loaded types, i.e. the compiler
+/// or the VM.
+pub trait TypeHierarchy {
+    /// Whether `derived` inherits from `base`, directly or indirectly.
+    fn is_subclass_of(&self, derived: &StringTypeReference, base: &StringTypeReference) -> bool;
+
+    /// Whether `inner` is nested, at any depth, in `outer`.
+    fn is_nested_in(&self, inner: &StringTypeReference, outer: &StringTypeReference) -> bool {
+        let (Some(inner_name), Some(outer_name)) = (inner.qualified_name(), outer.qualified_name())
+        else {
+            return false;
+        };
+        inner.assembly_name() == outer.assembly_name()
+            && std::iter::successors(inner_name.declaring_type(), |x| x.declaring_type())
+                .any(|x| x == outer_name)
+    }
+}
+
+/// Same type definition, ignoring generic instantiation.
+fn is_same_definition(a: &StringTypeReference, b: &StringTypeReference) -> bool {
+    match (a.qualified_name(), b.qualified_name()) {
+        (Some(a_name), Some(b_name)) => a.assembly_name() == b.assembly_name() && a_name == b_name,
+        _ => a == b,
+    }
+}
+
+impl Visibility {
+    /// Whether code in `accessor_type` (from `accessor_assembly`) may use a member with
+    /// this visibility declared in `declaring_type` (from `declaring_assembly`).
+    pub fn can_access<H: TypeHierarchy + ?Sized>(
+        self,
+        accessor_type: &StringTypeReference,
+        accessor_assembly: &StringName,
+        declaring_type: &StringTypeReference,
+        declaring_assembly: &StringName,
+        hierarchy: &H,
+    ) -> bool {
+        let in_declaring_type = || {
+            is_same_definition(accessor_type, declaring_type)
+                || hierarchy.is_nested_in(accessor_type, declaring_type)
+        };
+        let in_assembly = || accessor_assembly == declaring_assembly;
+        let in_subclass = || {
+            in_declaring_type()
+                || hierarchy.is_subclass_of(accessor_type, declaring_type)
+                || std::iter::successors(accessor_type.qualified_name(), |x| x.declaring_type())
+                    .skip(1)
+                    .any(|outer| {
+                        let outer = StringTypeReference::Single {
+                            assem: accessor_assembly.clone(),
+                            ty: outer.into_string_name(),
+                        };
+                        hierarchy.is_subclass_of(&outer, declaring_type)
+                    })
+        };
+        match self {
+            Visibility::Public => true,
+            Visibility::Private => in_declaring_type(),
+            Visibility::AssemblyOnly => in_assembly(),
+            Visibility::Protected => in_subclass(),
+            Visibility::ProtectedOrAssembly => in_assembly() || in_subclass(),
+            Visibility::ProtectedAndAssembly => in_assembly() && in_subclass(),
+        }
+    }
+
+    pub fn is_protected(self) -> bool {
+        matches!(
+            self,
+            Self::Protected | Self::ProtectedOrAssembly | Self::ProtectedAndAssembly
+        )
+    }
+
+    /// Whether the member can be reached from another assembly at all.
+    pub fn is_visible_outside_assembly(self) -> bool {
+        matches!(
+            self,
+            Self::Public | Self::Protected | Self::ProtectedOrAssembly
+        )
+    }
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, UnwrapEnum, WithType)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    UnwrapEnum,
+    WithType,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
 #[with_type(repr = u8)]
 #[with_type(derive = (TryFromPrimitive, IntoPrimitive, Clone, Copy))]
 #[unwrap_enum(ref, ref_mut)]
 pub enum TypeSpecificAttr {
-    Class(BitFlags<ClassImplementationFlags>),
-    Struct(BitFlags<StructImplementationFlags>),
-    Interface(BitFlags<InterfaceImplementationFlags>),
+    Class(
+        #[borsh(
+            serialize_with = "borsh_flags::serialize",
+            deserialize_with = "borsh_flags::deserialize"
+        )]
+        BitFlags<ClassImplementationFlags>,
+    ),
+    Struct(
+        #[borsh(
+            serialize_with = "borsh_flags::serialize",
+            deserialize_with = "borsh_flags::deserialize"
+        )]
+        BitFlags<StructImplementationFlags>,
+    ),
+    Interface(
+        #[borsh(
+            serialize_with = "borsh_flags::serialize",
+            deserialize_with = "borsh_flags::deserialize"
+        )]
+        BitFlags<InterfaceImplementationFlags>,
+    ),
 }
 
 #[bitflags]
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum StructImplementationFlags {
-    Ref,
+    Ref = 1 << 0,
+    ReadOnly = 1 << 1,
+    Record = 1 << 2,
+    /// Static fields may be initialized any time before the first static field access,
+    /// rather than exactly on first use of the type.
+    BeforeFieldInit = 1 << 3,
 }
 
 #[bitflags]
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum ClassImplementationFlags {
-    Static,
+    Static = 1 << 0,
+    Abstract = 1 << 1,
+    Sealed = 1 << 2,
+    Record = 1 << 3,
+    /// See [`StructImplementationFlags::BeforeFieldInit`].
+    BeforeFieldInit = 1 << 4,
 }
 
+/// There is no `None` flag: an interface without flags is `BitFlags::empty()`.
 #[bitflags]
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum InterfaceImplementationFlags {
-    None,
+    /// Only types in the declaring assembly may implement it.
+    Sealed = 1 << 0,
+    /// Declares no members and only tags its implementors.
+    Marker = 1 << 1,
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, ctor, CopyGetters, Setters, MutGetters)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    ctor,
+    CopyGetters,
+    Setters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
 #[ctor(pub new)]
 #[getset(set = "pub", get_mut = "pub")]
 #[get_copy = "pub"]
@@ -94,3 +655,403 @@ pub struct TypeAttr {
     vis: Visibility,
     specific: TypeSpecificAttr,
 }
+
+impl TypeAttr {
+    #[track_caller]
+    pub fn validate(&self) -> Result<(), GenericError<AttrError>> {
+        match self.specific {
+            TypeSpecificAttr::Class(flags) => {
+                use ClassImplementationFlags::*;
+                check_flags(
+                    flags,
+                    &[
+                        (Static, Abstract),
+                        (Static, Sealed),
+                        (Static, Record),
+                        (Abstract, Sealed),
+                    ],
+                    &[],
+                )
+            }
+            TypeSpecificAttr::Struct(flags) => {
+                use StructImplementationFlags::*;
+                check_flags(flags, &[(Ref, Record)], &[])
+            }
+            TypeSpecificAttr::Interface(flags) => {
+                use InterfaceImplementationFlags::*;
+                check_flags(flags, &[(Sealed, Marker)], &[])
+            }
+        }
+    }
+
+    /// Interfaces are always abstract.
+    pub fn is_abstract(&self) -> bool {
+        match self.specific {
+            TypeSpecificAttr::Class(flags) => flags.contains(ClassImplementationFlags::Abstract),
+            TypeSpecificAttr::Struct(_) => false,
+            TypeSpecificAttr::Interface(_) => true,
+        }
+    }
+
+    /// Structs are always sealed.
+    pub fn is_sealed(&self) -> bool {
+        match self.specific {
+            TypeSpecificAttr::Class(flags) => flags.contains(ClassImplementationFlags::Sealed),
+            TypeSpecificAttr::Struct(_) => true,
+            TypeSpecificAttr::Interface(flags) => {
+                flags.contains(InterfaceImplementationFlags::Sealed)
+            }
+        }
+    }
+
+    pub fn is_record(&self) -> bool {
+        match self.specific {
+            TypeSpecificAttr::Class(flags) => flags.contains(ClassImplementationFlags::Record),
+            TypeSpecificAttr::Struct(flags) => flags.contains(StructImplementationFlags::Record),
+            TypeSpecificAttr::Interface(_) => false,
+        }
+    }
+
+    pub fn is_before_field_init(&self) -> bool {
+        match self.specific {
+            TypeSpecificAttr::Class(flags) => {
+                flags.contains(ClassImplementationFlags::BeforeFieldInit)
+            }
+            TypeSpecificAttr::Struct(flags) => {
+                flags.contains(StructImplementationFlags::BeforeFieldInit)
+            }
+            TypeSpecificAttr::Interface(_) => false,
+        }
+    }
+}
+
+/// Fixed-size little-endian encoding of attribute headers.
+///
+/// The headers are embedded in container records, so the layout is versioned by
+/// [`crate::container::FORMAT_VERSION`]: changing it needs a version bump and a
+/// migration step. `test_attr_layout_golden` pins the bytes.
+///
+/// | type                             | bytes                                        |
+/// |----------------------------------|----------------------------------------------|
+/// | [`Visibility`]                   | 1, the discriminant                          |
+/// | `Field`/`Struct`/`Class`/`Interface` flags | 1, the bits                        |
+/// | [`MethodImplementationFlags`]    | 2, the bits                                  |
+/// | [`TypeSpecificAttr`]             | 1 kind (`TypeSpecificAttrType`), 1 flag bits |
+/// | [`FieldAttr`]                    | visibility, flags                            |
+/// | [`MethodAttr`]                   | visibility, flags, `register_len` as `u64`   |
+/// | [`TypeAttr`]                     | visibility, type-specific attr               |
+pub trait AttrLayout: Sized {
+    const ENCODED_LEN: usize;
+
+    fn encode_into(&self, out: &mut Vec<u8>);
+
+    /// Decodes the first [`Self::ENCODED_LEN`] bytes of `bytes`.
+    fn decode(bytes: &[u8]) -> Result<Self, GenericError<BinaryError>>;
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::ENCODED_LEN);
+        self.encode_into(&mut out);
+        out
+    }
+}
+
+#[track_caller]
+fn layout_bytes<const N: usize>(
+    bytes: &[u8],
+    at: usize,
+) -> Result<[u8; N], GenericError<BinaryError>> {
+    bytes
+        .get(at..at + N)
+        .map(|x| x.try_into().unwrap())
+        .ok_or(BinaryError::BinaryTooShort.throw())
+}
+
+macro impl_flags_layout($($flags:ident: $bits:ty),* $(,)?) {
+    $(
+        impl AttrLayout for BitFlags<$flags> {
+            const ENCODED_LEN: usize = size_of::<$bits>();
+
+            fn encode_into(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.bits().to_le_bytes());
+            }
+            fn decode(bytes: &[u8]) -> Result<Self, GenericError<BinaryError>> {
+                let bits = <$bits>::from_le_bytes(layout_bytes(bytes, 0)?);
+                BitFlags::from_bits(bits)
+                    .map_err(|_| BinaryError::EnumOutOfBounds(stringify!($flags)).throw())
+            }
+        }
+    )*
+}
+
+impl_flags_layout! {
+    FieldImplementationFlags: u8,
+    MethodImplementationFlags: u16,
+    StructImplementationFlags: u8,
+    ClassImplementationFlags: u8,
+    InterfaceImplementationFlags: u8,
+}
+
+impl AttrLayout for Visibility {
+    const ENCODED_LEN: usize = 1;
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+    fn decode(bytes: &[u8]) -> Result<Self, GenericError<BinaryError>> {
+        let [x] = layout_bytes(bytes, 0)?;
+        Self::try_from(x).map_err(|_| BinaryError::EnumOutOfBounds("Visibility").throw())
+    }
+}
+
+impl AttrLayout for TypeSpecificAttr {
+    const ENCODED_LEN: usize = 2;
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(self.to_type()));
+        match self {
+            TypeSpecificAttr::Class(flags) => flags.encode_into(out),
+            TypeSpecificAttr::Struct(flags) => flags.encode_into(out),
+            TypeSpecificAttr::Interface(flags) => flags.encode_into(out),
+        }
+    }
+    fn decode(bytes: &[u8]) -> Result<Self, GenericError<BinaryError>> {
+        let [kind, _] = layout_bytes(bytes, 0)?;
+        let flags = &bytes[1..];
+        Ok(match TypeSpecificAttrType::try_from(kind) {
+            Ok(TypeSpecificAttrType::Class) => TypeSpecificAttr::Class(AttrLayout::decode(flags)?),
+            Ok(TypeSpecificAttrType::Struct) => {
+                TypeSpecificAttr::Struct(AttrLayout::decode(flags)?)
+            }
+            Ok(TypeSpecificAttrType::Interface) => {
+                TypeSpecificAttr::Interface(AttrLayout::decode(flags)?)
+            }
+            Err(_) => {
+                return Err(BinaryError::UnexpectedTypeSpecificAttr("unknown kind").throw());
+            }
+        })
+    }
+}
+
+impl AttrLayout for FieldAttr {
+    const ENCODED_LEN: usize = 2;
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        self.vis.encode_into(out);
+        self.impl_flags.encode_into(out);
+    }
+    fn decode(bytes: &[u8]) -> Result<Self, GenericError<BinaryError>> {
+        Ok(Self::new(
+            AttrLayout::decode(bytes)?,
+            AttrLayout::decode(bytes.get(1..).unwrap_or_default())?,
+        ))
+    }
+}
+
+impl AttrLayout for MethodAttr {
+    const ENCODED_LEN: usize = 11;
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        self.vis.encode_into(out);
+        self.impl_flags.encode_into(out);
+        out.extend_from_slice(&self.register_len.to_le_bytes());
+    }
+    fn decode(bytes: &[u8]) -> Result<Self, GenericError<BinaryError>> {
+        Ok(Self::new(
+            AttrLayout::decode(bytes)?,
+            AttrLayout::decode(bytes.get(1..).unwrap_or_default())?,
+            u64::from_le_bytes(layout_bytes(bytes, 3)?),
+        ))
+    }
+}
+
+impl AttrLayout for TypeAttr {
+    const ENCODED_LEN: usize = 3;
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        self.vis.encode_into(out);
+        self.specific.encode_into(out);
+    }
+    fn decode(bytes: &[u8]) -> Result<Self, GenericError<BinaryError>> {
+        Ok(Self::new(
+            AttrLayout::decode(bytes)?,
+            AttrLayout::decode(bytes.get(1..).unwrap_or_default())?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Hierarchy;
+
+    impl TypeHierarchy for Hierarchy {
+        fn is_subclass_of(
+            &self,
+            derived: &StringTypeReference,
+            base: &StringTypeReference,
+        ) -> bool {
+            derived.string_name_repr().as_str() == "[App]Derived"
+                && base.string_name_repr().as_str() == "[Lib]Base"
+        }
+    }
+
+    fn ty(s: &str) -> StringTypeReference {
+        StringTypeReference::from_string_repr(s).unwrap()
+    }
+
+    #[test]
+    fn test_can_access() {
+        let base = ty("[Lib]Base");
+        let lib = StringName::from("Lib");
+        let check = |vis: Visibility, accessor: &str| {
+            let accessor = ty(accessor);
+            let accessor_assembly = accessor.assembly_name().unwrap().clone();
+            vis.can_access(&accessor, &accessor_assembly, &base, &lib, &Hierarchy)
+        };
+        assert!(check(Visibility::Private, "[Lib]Base"));
+        assert!(check(Visibility::Private, "[Lib]Base+Inner+Deeper"));
+        assert!(!check(Visibility::Private, "[Lib]Other"));
+        assert!(check(Visibility::AssemblyOnly, "[Lib]Other"));
+        assert!(!check(Visibility::AssemblyOnly, "[App]Derived"));
+        assert!(check(Visibility::Protected, "[App]Derived"));
+        assert!(check(Visibility::Protected, "[App]Derived+Inner"));
+        assert!(!check(Visibility::Protected, "[Lib]Other"));
+        assert!(check(Visibility::ProtectedOrAssembly, "[Lib]Other"));
+        assert!(check(Visibility::ProtectedOrAssembly, "[App]Derived"));
+        assert!(!check(Visibility::ProtectedAndAssembly, "[App]Derived"));
+        assert!(!check(Visibility::ProtectedAndAssembly, "[Lib]Other"));
+        assert!(check(Visibility::ProtectedAndAssembly, "[Lib]Base+Inner"));
+        assert!(check(Visibility::Public, "[App]Other"));
+    }
+
+    #[test]
+    fn test_method_flags_validation() {
+        use MethodImplementationFlags::*;
+        let method = |flags: BitFlags<MethodImplementationFlags>| {
+            MethodAttr::new(Visibility::Public, flags, 0)
+        };
+        assert!(method(Static.into()).validate(true).is_ok());
+        assert!(method(Virtual | Abstract).validate(false).is_ok());
+        assert!(method(Virtual | Override | Sealed).validate(true).is_ok());
+        assert!(method(Static | Virtual).validate(true).is_err());
+        assert!(method(Virtual | Abstract).validate(true).is_err());
+        assert!(method(Abstract.into()).validate(false).is_err());
+        assert!(method(Virtual | Sealed).validate(true).is_err());
+        assert!(method(Native.into()).validate(true).is_err());
+        assert!(method(BitFlags::empty()).validate(false).is_err());
+        assert!(method(Virtual | Override).is_override());
+    }
+
+    #[test]
+    fn test_type_flags_validation() {
+        use ClassImplementationFlags as C;
+        let ty = |specific| TypeAttr::new(Visibility::Public, specific);
+        assert!(
+            ty(TypeSpecificAttr::Class(C::Abstract | C::Record))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            ty(TypeSpecificAttr::Class(C::Static | C::Sealed))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            ty(TypeSpecificAttr::Class(C::Abstract | C::Sealed))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            ty(TypeSpecificAttr::Struct(
+                StructImplementationFlags::Ref | StructImplementationFlags::ReadOnly
+            ))
+            .validate()
+            .is_ok()
+        );
+        assert!(
+            ty(TypeSpecificAttr::Struct(
+                StructImplementationFlags::Ref | StructImplementationFlags::Record
+            ))
+            .validate()
+            .is_err()
+        );
+        assert!(ty(TypeSpecificAttr::Interface(BitFlags::empty())).is_abstract());
+    }
+
+    #[test]
+    fn test_field_flags_validation() {
+        use FieldImplementationFlags::*;
+        let field =
+            |flags: BitFlags<FieldImplementationFlags>| FieldAttr::new(Visibility::Public, flags);
+        assert!(FieldAttr::new_const(Visibility::Public).validate().is_ok());
+        assert!(field(Static | ThreadStatic).validate().is_ok());
+        assert!(field(ReadOnly | Transient).validate().is_ok());
+        assert!(field(Const.into()).validate().is_err());
+        assert!(field(ThreadStatic.into()).validate().is_err());
+        assert!(field(ReadOnly | Volatile).validate().is_err());
+
+        let value = ConstValue::Float64(f64::NAN);
+        let decoded = borsh::from_slice::<ConstValue>(&borsh::to_vec(&value).unwrap()).unwrap();
+        assert!(matches!(decoded, ConstValue::Float64(x) if x.is_nan()));
+
+        // `[[[…]]]` one element per level, without ever reaching the innermost value
+        let mut level = borsh::to_vec(&ConstValue::Array(
+            StringTypeReference::Generic("T".into()),
+            vec![],
+        ))
+        .unwrap();
+        level.truncate(level.len() - 4);
+        level.extend_from_slice(&1u32.to_le_bytes());
+        let nested = level.repeat(1_000_000);
+        assert!(borsh::from_slice::<ConstValue>(&nested).is_err());
+    }
+
+    // Fails on any layout change; see `AttrLayout` before updating the bytes.
+    #[test]
+    fn test_attr_layout_golden() {
+        use ClassImplementationFlags as C;
+        use MethodImplementationFlags as M;
+
+        let field = FieldAttr::new(
+            Visibility::Protected,
+            FieldImplementationFlags::Static | FieldImplementationFlags::ReadOnly,
+        );
+        assert_eq!(field.encode(), [3, 0b11]);
+        let method = MethodAttr::new(Visibility::Public, M::Virtual | M::Constructor, 0x0102);
+        assert_eq!(
+            method.encode(),
+            [0, 0b100, 0b1, 0x02, 0x01, 0, 0, 0, 0, 0, 0]
+        );
+        let ty = TypeAttr::new(
+            Visibility::AssemblyOnly,
+            TypeSpecificAttr::Class(C::Abstract | C::BeforeFieldInit),
+        );
+        assert_eq!(ty.encode(), [2, 0, 0b10010]);
+        assert_eq!(
+            TypeAttr::new(
+                Visibility::Public,
+                TypeSpecificAttr::Interface(InterfaceImplementationFlags::Marker.into())
+            )
+            .encode(),
+            [0, 2, 0b10]
+        );
+
+        let decoded = MethodAttr::decode(&method.encode()).unwrap();
+        assert_eq!(decoded.impl_flags(), method.impl_flags());
+        assert_eq!(decoded.register_len(), 0x0102);
+        assert_eq!(
+            FieldAttr::decode(&field.encode()).unwrap().impl_flags(),
+            field.impl_flags()
+        );
+        assert!(matches!(
+            TypeAttr::decode(&ty.encode()).unwrap().specific(),
+            TypeSpecificAttr::Class(x) if x == C::Abstract | C::BeforeFieldInit
+        ));
+
+        assert!(Visibility::decode(&[6]).is_err());
+        assert!(FieldAttr::decode(&[0, 0b1000_0000]).is_err());
+        assert!(TypeAttr::decode(&[0, 3, 0]).is_err());
+        assert!(MethodAttr::decode(&method.encode()[..10]).is_err());
+    }
+}
@@ -65,19 +65,27 @@ pub mod runtime {
 
 pub mod compiler {
     use bon::Builder;
-    use getset::Getters;
+    use getset::{CopyGetters, Getters};
     use serde::{Deserialize, Serialize};
 
-    #[derive(Getters, Builder, Clone, Debug, Deserialize, Serialize)]
+    #[derive(Getters, CopyGetters, Builder, Clone, Debug, Deserialize, Serialize)]
     #[getset(get = "pub")]
     pub struct CompilerConfig {
         stdlib_dir: String,
+        /// Compress the sections of written assemblies, see
+        /// [`crate::container::WriteOptions`].
+        #[builder(default)]
+        #[serde(default)]
+        #[getset(skip)]
+        #[get_copy = "pub"]
+        compress_sections: bool,
     }
 
     impl Default for CompilerConfig {
         fn default() -> Self {
             Self {
                 stdlib_dir: crate::path_searcher::get_stdlib_dir().unwrap(),
+                compress_sections: false,
             }
         }
     }
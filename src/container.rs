@@ -0,0 +1,988 @@
+//! On-disk assembly container.
+//!
+//! All integers are little-endian.
+//!
+//! ```text
+//! file      := header directory section*
+//! header    := magic "PLAS" | format_version: u16 | section_count: u16 | content_hash: [u8; 32]
+//! directory := section_count × (kind: u16 | flags: u16 | offset: u64 | length: u64 | crc32: u32)
+//! ```
+//!
+//! `flags` are [`SectionFlags`]; a `Compressed` section is stored as an LZ4 block,
+//! which [`read_assembly`] decompresses and [`decompress`] expands for [`AssemblyView`].
+//!
+//! `crc32` covers the stored bytes of its section. `content_hash` is the [`ContentHash`]
+//! of all sections before compression; it depends neither on their offsets, their
+//! compression nor the format version. Both are checked by [`read_assembly`] and
+//! [`AssemblyView::verify`]. Version 1 files have neither field and are still read.
+//!
+//! Files newer than [`FORMAT_VERSION`] are rejected with `UnsupportedVersion`. Older ones
+//! are upgraded by [`read_assembly`] through [`MigrationRegistry`].
+//!
+//! | section    | content                                                          |
+//! |------------|------------------------------------------------------------------|
+//! | `Strings`  | [`SymbolTable`] layout; `str` fields below index it              |
+//! | `Blobs`    | the same layout over borsh payloads; `blob` fields index it      |
+//! | `Assembly` | `name: str`                                                      |
+//! | `Types`    | `count: u32`, then `count` type records                          |
+//! | `Fields`   | `count: u32`, then `count` field records                         |
+//! | `Methods`  | `count: u32`, then `count` method records                        |
+//! | `Code`     | borsh `Vec<StringInstruction>` of every method body, back to back |
+//! | `Manifest` | `version: string`, then borsh `Vec<AssemblyReference>`           |
+//!
+//! ```text
+//! type   := name: str | attr | parent: blob | interfaces: blob | extra: blob
+//!           | first_field: u32 | field_count: u32 | first_method: u32 | method_count: u32
+//! field  := name: str | attr | ty: blob | extra: blob
+//! method := name: str | attr | signature: blob | extra: blob | code_offset: u32 | code_len: u32
+//! ```
+//!
+//! `attr` is the [`AttrLayout`] header; the variable-sized rest of the definition is the
+//! `extra` blob: `(custom_attrs, generic_params)` for types and methods,
+//! `(const_value, custom_attrs)` for fields. A method without a body has `code_len` 0.
+//!
+//! `Manifest` is optional; without it the assembly is version 0.0.0 and references
+//! nothing.
+//!
+//! `string` is a LEB128 length followed by UTF-8, as written by [`BinaryWriter::write_str`].
+//!
+//! [`SymbolTable`]: crate::SymbolTable
+
+use crate::assembly_view::AssemblyView;
+use crate::attrs::{AttrLayout, FieldAttr, HasCustomAttributes, MethodAttr, TypeAttr};
+use crate::configs::compiler::CompilerConfig;
+use crate::errors::{BinaryError, GenericError};
+use crate::generics::HasGenericParams;
+use crate::io_utils::{BinaryReader, BinaryWriter};
+use crate::manifest::AssemblyReference;
+use crate::metadata::{AssemblyDef, FieldDef, MethodDef, TypeDef};
+use crate::migration::MigrationRegistry;
+use crate::{StringName, SymbolTable};
+use bon::Builder;
+use borsh::{BorshDeserialize, BorshSerialize};
+use enumflags2::{BitFlags, bitflags};
+use getset::CopyGetters;
+use indexmap::IndexSet;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Write;
+use std::ops::Range;
+
+pub const MAGIC: [u8; 4] = *b"PLAS";
+pub const FORMAT_VERSION: u16 = 2;
+
+const HEADER_LEN: usize = 8 + 32;
+const DIRECTORY_ENTRY_LEN: usize = 24;
+const V1_HEADER_LEN: usize = 8;
+const V1_DIRECTORY_ENTRY_LEN: usize = 20;
+/// Every sequence of an LZ4 block yields at most 255 bytes per input byte.
+const MAX_LZ4_RATIO: usize = 255;
+pub(crate) const TYPE_RECORD_LEN: usize = 4 + TypeAttr::ENCODED_LEN + 3 * 4 + 4 * 4;
+pub(crate) const FIELD_RECORD_LEN: usize = 4 + FieldAttr::ENCODED_LEN + 2 * 4;
+pub(crate) const METHOD_RECORD_LEN: usize = 4 + MethodAttr::ENCODED_LEN + 4 * 4;
+
+#[repr(u16)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, TryFromPrimitive, IntoPrimitive)]
+pub enum SectionKind {
+    Strings = 1,
+    Blobs = 2,
+    Assembly = 3,
+    Types = 4,
+    Fields = 5,
+    Methods = 6,
+    Code = 7,
+    Manifest = 8,
+}
+
+#[bitflags]
+#[repr(u16)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SectionFlags {
+    /// Stored as an LZ4 block prefixed by its decompressed length as `u32`.
+    Compressed = 1 << 0,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Builder, CopyGetters)]
+#[get_copy = "pub"]
+pub struct WriteOptions {
+    /// Compresses every section that gets smaller by it.
+    #[builder(default)]
+    compress_sections: bool,
+}
+
+impl From<&CompilerConfig> for WriteOptions {
+    fn from(config: &CompilerConfig) -> Self {
+        Self {
+            compress_sections: config.compress_sections(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CopyGetters)]
+#[get_copy = "pub"]
+pub struct SectionEntry {
+    kind: SectionKind,
+    flags: BitFlags<SectionFlags>,
+    offset: u64,
+    length: u64,
+    /// `None` in version 1 files.
+    crc32: Option<u32>,
+}
+
+/// SHA-256 over every section as `kind: u16 | length: u64 | bytes`, in directory order.
+///
+/// Identifies an assembly's content, e.g. as a cache key or to check that a build is
+/// reproducible.
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+pub struct ContentHash([u8; 32]);
+
+impl ContentHash {
+    pub const fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+    fn of<'s>(sections: impl IntoIterator<Item = (SectionKind, &'s [u8])>) -> Self {
+        let mut hasher = Sha256::new();
+        for (kind, bytes) in sections {
+            hasher.update(u16::from(kind).to_le_bytes());
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        }
+        Self(hasher.finalize().into())
+    }
+}
+
+impl Display for ContentHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|x| write!(f, "{x:02x}"))
+    }
+}
+
+impl Debug for ContentHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContentHash({self})")
+    }
+}
+
+/// Serializes `assembly` and streams it into `writer`.
+pub fn write_assembly<W: Write>(assembly: &AssemblyDef, writer: &mut W) -> std::io::Result<()> {
+    write_assembly_with(assembly, WriteOptions::default(), writer)
+}
+
+pub fn write_assembly_with<W: Write>(
+    assembly: &AssemblyDef,
+    options: WriteOptions,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    write_sections_as(
+        FORMAT_VERSION,
+        &Encoder::default().encode(assembly),
+        options,
+        writer,
+    )
+}
+
+pub fn encode_assembly(assembly: &AssemblyDef) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_assembly(assembly, &mut out).unwrap();
+    out
+}
+
+/// Returns `bytes` unchanged if no section is compressed, and otherwise the same file
+/// with every section decompressed, as [`AssemblyView`] reads sections in place.
+#[track_caller]
+pub fn decompress(bytes: &[u8]) -> Result<Cow<'_, [u8]>, GenericError<BinaryError>> {
+    let directory = Directory::parse(bytes)?;
+    if !directory.is_compressed() {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    let sections = directory
+        .contents()?
+        .into_iter()
+        .map(|(kind, bytes)| (kind, bytes.into_owned()))
+        .collect::<Vec<_>>();
+    let mut out = Vec::new();
+    write_sections_as(
+        directory.version(),
+        &sections,
+        WriteOptions::default(),
+        &mut out,
+    )
+    .unwrap();
+    Ok(Cow::Owned(out))
+}
+
+/// The hash [`write_assembly`] would store for `assembly`.
+pub fn content_hash(assembly: &AssemblyDef) -> ContentHash {
+    let sections = Encoder::default().encode(assembly);
+    ContentHash::of(
+        sections
+            .iter()
+            .map(|(kind, bytes)| (*kind, bytes.as_slice())),
+    )
+}
+
+/// Parses, verifies and validates a container produced by [`write_assembly`] into owned
+/// definitions; see [`AssemblyView`] to read it in place.
+#[track_caller]
+pub fn read_assembly(bytes: &[u8]) -> Result<AssemblyDef, GenericError<BinaryError>> {
+    read_assembly_with(bytes, &MigrationRegistry::builtin())
+}
+
+/// [`read_assembly`], upgrading older format versions through `migrations`.
+#[track_caller]
+pub fn read_assembly_with(
+    bytes: &[u8],
+    migrations: &MigrationRegistry,
+) -> Result<AssemblyDef, GenericError<BinaryError>> {
+    let directory = Directory::parse(bytes)?;
+    directory.verify()?;
+    let upgraded;
+    let view = if directory.version() == FORMAT_VERSION && !directory.is_compressed() {
+        AssemblyView::new(directory)?
+    } else {
+        upgraded = migrations.rewrite(&directory)?;
+        AssemblyView::parse(&upgraded)?
+    };
+    let assembly = view.to_assembly_def()?;
+    assembly
+        .validate()
+        .map_err(|e| BinaryError::InvalidAttr(e.into_error()).throw())?;
+    Ok(assembly)
+}
+
+pub(crate) fn write_sections<W: Write>(
+    sections: &[(SectionKind, Vec<u8>)],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    write_sections_as(FORMAT_VERSION, sections, WriteOptions::default(), writer)
+}
+
+/// Writes the header and directory layout of `version`, which tests use to produce
+/// files in older versions.
+pub(crate) fn write_sections_as<W: Write>(
+    version: u16,
+    sections: &[(SectionKind, Vec<u8>)],
+    options: WriteOptions,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let (header_len, entry_len) = match version {
+        1 => (V1_HEADER_LEN, V1_DIRECTORY_ENTRY_LEN),
+        _ => (HEADER_LEN, DIRECTORY_ENTRY_LEN),
+    };
+    let mut header = BinaryWriter::new(Vec::with_capacity(header_len + sections.len() * entry_len));
+    header.write_bytes(&MAGIC)?;
+    header.write_u16(version)?;
+    header.write_u16(sections.len() as u16)?;
+    if version > 1 {
+        let hash = ContentHash::of(
+            sections
+                .iter()
+                .map(|(kind, bytes)| (*kind, bytes.as_slice())),
+        );
+        header.write_bytes(hash.as_bytes())?;
+    }
+    let stored = sections
+        .iter()
+        .map(|(_, bytes)| match options.compress_sections {
+            true => Some(lz4_flex::compress_prepend_size(bytes)).filter(|x| x.len() < bytes.len()),
+            false => None,
+        })
+        .collect::<Vec<_>>();
+    let mut offset = (header_len + sections.len() * entry_len) as u64;
+    for ((kind, bytes), compressed) in sections.iter().zip(&stored) {
+        let (flags, bytes) = match compressed {
+            Some(x) => (SectionFlags::Compressed.into(), x),
+            None => (BitFlags::<SectionFlags>::empty(), bytes),
+        };
+        header.write_u16(u16::from(*kind))?;
+        header.write_u16(flags.bits())?;
+        header.write_u64(offset)?;
+        header.write_u64(bytes.len() as u64)?;
+        if version > 1 {
+            header.write_u32(crc32fast::hash(bytes))?;
+        }
+        offset += bytes.len() as u64;
+    }
+    let header = header.into_bytes();
+    let parts = std::iter::once(&header)
+        .chain(
+            sections
+                .iter()
+                .zip(&stored)
+                .map(|((_, bytes), compressed)| compressed.as_ref().unwrap_or(bytes)),
+        )
+        .map(Vec::as_slice)
+        .collect::<Vec<_>>();
+    BinaryWriter::new(writer).write_vectored(&parts)
+}
+
+/// Insertion-ordered, deduplicated borsh payloads.
+#[derive(Default)]
+struct BlobTable {
+    blobs: IndexSet<Vec<u8>>,
+}
+
+impl BlobTable {
+    fn insert<T: BorshSerialize>(&mut self, value: &T) -> u32 {
+        self.blobs.insert_full(borsh::to_vec(value).unwrap()).0 as u32
+    }
+    fn encode(&self) -> Vec<u8> {
+        let mut out = BinaryWriter::new(Vec::new());
+        out.write_u32(self.blobs.len() as u32).unwrap();
+        let mut end = 0u32;
+        for blob in self.blobs.iter() {
+            end += blob.len() as u32;
+            out.write_u32(end).unwrap();
+        }
+        let blobs = self.blobs.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        out.write_vectored(&blobs).unwrap();
+        out.into_bytes()
+    }
+}
+
+/// Records of one kind, prefixed by their count once finished.
+struct RecordWriter {
+    count: u32,
+    out: BinaryWriter<Vec<u8>>,
+}
+
+impl Default for RecordWriter {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            out: BinaryWriter::new(Vec::new()),
+        }
+    }
+}
+
+impl RecordWriter {
+    fn push(&mut self, name: u32, attr: &impl AttrLayout, slots: &[u32]) {
+        self.out.write_u32(name).unwrap();
+        self.out.write_bytes(&attr.encode()).unwrap();
+        slots.iter().for_each(|x| self.out.write_u32(*x).unwrap());
+        self.count += 1;
+    }
+    fn finish(self) -> Vec<u8> {
+        let mut out = BinaryWriter::new(Vec::new());
+        out.write_u32(self.count).unwrap();
+        out.write_bytes(self.out.get_ref()).unwrap();
+        out.into_bytes()
+    }
+}
+
+#[derive(Default)]
+struct Encoder {
+    strings: SymbolTable,
+    blobs: BlobTable,
+    types: RecordWriter,
+    fields: RecordWriter,
+    methods: RecordWriter,
+    code: Vec<u8>,
+}
+
+impl Encoder {
+    fn encode(mut self, assembly: &AssemblyDef) -> Vec<(SectionKind, Vec<u8>)> {
+        let name = self.string(assembly.name());
+        assembly.types().iter().for_each(|x| self.encode_type(x));
+        vec![
+            (SectionKind::Strings, self.strings.encode()),
+            (SectionKind::Blobs, self.blobs.encode()),
+            (SectionKind::Assembly, name.to_le_bytes().to_vec()),
+            (SectionKind::Types, self.types.finish()),
+            (SectionKind::Fields, self.fields.finish()),
+            (SectionKind::Methods, self.methods.finish()),
+            (SectionKind::Code, self.code),
+            (SectionKind::Manifest, encode_manifest(assembly)),
+        ]
+    }
+    fn string(&mut self, s: &StringName) -> u32 {
+        self.strings.intern(s.as_str()).index()
+    }
+    fn encode_type(&mut self, ty: &TypeDef) {
+        let first_field = self.fields.count;
+        let first_method = self.methods.count;
+        ty.fields().iter().for_each(|x| self.encode_field(x));
+        ty.methods().iter().for_each(|x| self.encode_method(x));
+
+        let attr = ty.attr();
+        let slots = [
+            self.blobs.insert(ty.parent()),
+            self.blobs.insert(ty.interfaces()),
+            self.blobs.insert(&(ty.custom_attrs(), ty.generic_params())),
+            first_field,
+            self.fields.count - first_field,
+            first_method,
+            self.methods.count - first_method,
+        ];
+        let name = self.string(ty.name());
+        self.types.push(name, attr, &slots);
+    }
+    fn encode_field(&mut self, field: &FieldDef) {
+        let attr = field.attr();
+        let slots = [
+            self.blobs.insert(field.ty()),
+            self.blobs
+                .insert(&(field.const_value(), field.custom_attrs())),
+        ];
+        let name = self.string(field.name());
+        self.fields.push(name, attr, &slots);
+    }
+    fn encode_method(&mut self, method: &MethodDef) {
+        let attr = method.attr();
+        let code_offset = self.code.len() as u32;
+        if method.has_body() {
+            BorshSerialize::serialize(method.body(), &mut self.code).unwrap();
+        }
+        let slots = [
+            self.blobs.insert(method.signature()),
+            self.blobs
+                .insert(&(method.custom_attrs(), method.generic_params())),
+            code_offset,
+            self.code.len() as u32 - code_offset,
+        ];
+        let name = self.string(method.name());
+        self.methods.push(name, attr, &slots);
+    }
+}
+
+fn encode_manifest(assembly: &AssemblyDef) -> Vec<u8> {
+    let mut out = BinaryWriter::new(Vec::new());
+    out.write_str(&assembly.version().to_string()).unwrap();
+    out.write_bytes(&borsh::to_vec(assembly.references()).unwrap())
+        .unwrap();
+    out.into_bytes()
+}
+
+/// The version and references stored in a `Manifest` section.
+#[track_caller]
+pub(crate) fn decode_manifest(
+    bytes: &[u8],
+) -> Result<(Version, Vec<AssemblyReference>), GenericError<BinaryError>> {
+    let mut reader = BinaryReader::new(bytes);
+    let version = reader
+        .take_str()?
+        .parse()
+        .map_err(|_| BinaryError::WrongFileFormat.throw())?;
+    let references = borsh::from_slice(reader.remaining())
+        .map_err(|_| reader.error(BinaryError::WrongFileFormat))?;
+    Ok((version, references))
+}
+
+/// A `Strings` or `Blobs` section, checked once so lookups only need an index check.
+pub(crate) struct Table<'a> {
+    ends: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> Table<'a> {
+    #[track_caller]
+    pub(crate) fn parse(bytes: &'a [u8]) -> Result<Self, GenericError<BinaryError>> {
+        let mut reader = BinaryReader::new(bytes);
+        let count = reader.read_u32()? as usize;
+        let table = Self {
+            ends: reader.take(count * 4)?,
+            data: reader.remaining(),
+        };
+        let mut start = 0;
+        for i in 0..count {
+            let end = table.end(i);
+            if end < start || end > table.data.len() {
+                return Err(BinaryError::BinaryTooShort.throw());
+            }
+            start = end;
+        }
+        Ok(table)
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.ends.len() / 4
+    }
+    fn end(&self, i: usize) -> usize {
+        u32::from_le_bytes(self.ends[i * 4..i * 4 + 4].try_into().unwrap()) as usize
+    }
+    pub(crate) fn get(&self, index: u32) -> Option<&'a [u8]> {
+        let i = index as usize;
+        if i >= self.len() {
+            return None;
+        }
+        let start = if i == 0 { 0 } else { self.end(i - 1) };
+        Some(&self.data[start..self.end(i)])
+    }
+    #[track_caller]
+    pub(crate) fn str(&self, index: u32) -> Result<&'a str, GenericError<BinaryError>> {
+        let bytes = self.get(index).ok_or(
+            BinaryError::StringNotFound {
+                index: index as u64,
+            }
+            .throw(),
+        )?;
+        str::from_utf8(bytes).map_err(|_| BinaryError::WrongFileFormat.throw())
+    }
+    #[track_caller]
+    pub(crate) fn blob<T: BorshDeserialize>(
+        &self,
+        index: u32,
+    ) -> Result<T, GenericError<BinaryError>> {
+        let bytes = self
+            .get(index)
+            .ok_or(BinaryError::IndexOutOfRange.throw())?;
+        borsh::from_slice(bytes).map_err(|_| BinaryError::WrongFileFormat.throw())
+    }
+}
+
+/// Fixed-size records after a `count: u32` prefix.
+pub(crate) struct Records<'a> {
+    data: &'a [u8],
+    record_len: usize,
+}
+
+impl<'a> Records<'a> {
+    #[track_caller]
+    pub(crate) fn parse(
+        bytes: &'a [u8],
+        record_len: usize,
+    ) -> Result<Self, GenericError<BinaryError>> {
+        let mut reader = BinaryReader::new(bytes);
+        let count = reader.read_u32()? as usize;
+        let data = reader.take(count * record_len)?;
+        if !reader.is_empty() {
+            return Err(reader.error(BinaryError::WrongFileFormat));
+        }
+        Ok(Self { data, record_len })
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.data.len() / self.record_len
+    }
+    #[track_caller]
+    pub(crate) fn get(&self, index: u32) -> Result<&'a [u8], GenericError<BinaryError>> {
+        let start = index as usize * self.record_len;
+        self.data
+            .get(start..start + self.record_len)
+            .ok_or(BinaryError::IndexOutOfRange.throw())
+    }
+    /// Records `first..first + count`, as referenced from a type record.
+    #[track_caller]
+    pub(crate) fn range(
+        &self,
+        first: u32,
+        count: u32,
+    ) -> Result<Range<u32>, GenericError<BinaryError>> {
+        match first.checked_add(count) {
+            Some(end) if end as usize <= self.len() => Ok(first..end),
+            _ => Err(BinaryError::IndexOutOfRange.throw()),
+        }
+    }
+}
+
+pub(crate) type Contents<'a> = Vec<(SectionKind, Cow<'a, [u8]>)>;
+
+/// Header and section directory, checked against the size of the file. The stored
+/// checksums are only checked by [`Directory::verify`].
+pub(crate) struct Directory<'a> {
+    bytes: &'a [u8],
+    version: u16,
+    entries: Vec<SectionEntry>,
+    stored_hash: Option<ContentHash>,
+}
+
+impl<'a> Directory<'a> {
+    #[track_caller]
+    pub(crate) fn parse(bytes: &'a [u8]) -> Result<Self, GenericError<BinaryError>> {
+        let mut reader = BinaryReader::new(bytes);
+        if reader.read_array::<4>()? != MAGIC {
+            return Err(BinaryError::WrongFileFormat.throw());
+        }
+        let version = reader.read_u16()?;
+        if version > FORMAT_VERSION {
+            return Err(BinaryError::UnsupportedVersion {
+                found: version,
+                supported: FORMAT_VERSION,
+            }
+            .throw());
+        }
+        if version == 0 {
+            return Err(BinaryError::WrongFileFormat.throw());
+        }
+        let count = reader.read_u16()?;
+        let stored_hash = match version {
+            1 => None,
+            _ => Some(ContentHash(reader.read_array()?)),
+        };
+        let mut entries = Vec::<SectionEntry>::with_capacity(count as usize);
+        for _ in 0..count {
+            let entry = SectionEntry {
+                kind: SectionKind::try_from(reader.read_u16()?)
+                    .map_err(|_| reader.error(BinaryError::EnumOutOfBounds("SectionKind")))?,
+                flags: BitFlags::from_bits(reader.read_u16()?)
+                    .map_err(|_| reader.error(BinaryError::WrongFileFormat))?,
+                offset: reader.read_u64()?,
+                length: reader.read_u64()?,
+                crc32: match version {
+                    1 => None,
+                    _ => Some(reader.read_u32()?),
+                },
+            };
+            if entries.iter().any(|x| x.kind == entry.kind) {
+                return Err(BinaryError::WrongFileFormat.throw());
+            }
+            match entry.offset.checked_add(entry.length) {
+                Some(end) if end <= bytes.len() as u64 => {}
+                _ => return Err(BinaryError::BinaryTooShort.throw()),
+            }
+            entries.push(entry);
+        }
+        Ok(Self {
+            bytes,
+            version,
+            entries,
+            stored_hash,
+        })
+    }
+    /// Checks the section checksums and the stored content hash, and returns the hash of
+    /// the contents, which version 1 files do not store.
+    #[track_caller]
+    pub(crate) fn verify(&self) -> Result<ContentHash, GenericError<BinaryError>> {
+        for entry in &self.entries {
+            if entry
+                .crc32
+                .is_some_and(|crc| crc != crc32fast::hash(stored(self.bytes, entry)))
+            {
+                return Err(BinaryError::ChecksumMismatch(entry.kind)
+                    .at(entry.offset)
+                    .throw());
+            }
+        }
+        let contents = self.contents()?;
+        let content_hash = ContentHash::of(contents.iter().map(|(kind, x)| (*kind, x.as_ref())));
+        if self.stored_hash.is_some_and(|x| x != content_hash) {
+            return Err(BinaryError::ContentHashMismatch.at(8).throw());
+        }
+        Ok(content_hash)
+    }
+    pub(crate) fn is_compressed(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|x| x.flags.contains(SectionFlags::Compressed))
+    }
+    pub(crate) fn version(&self) -> u16 {
+        self.version
+    }
+    pub(crate) fn entries(&self) -> &[SectionEntry] {
+        &self.entries
+    }
+    /// `None` for version 1 files.
+    pub(crate) fn stored_hash(&self) -> Option<ContentHash> {
+        self.stored_hash
+    }
+    /// Every section in directory order, decompressed.
+    #[track_caller]
+    pub(crate) fn contents(&self) -> Result<Contents<'a>, GenericError<BinaryError>> {
+        self.entries
+            .iter()
+            .map(|x| {
+                let bytes = stored(self.bytes, x);
+                if !x.flags.contains(SectionFlags::Compressed) {
+                    return Ok((x.kind, Cow::Borrowed(bytes)));
+                }
+                decompress_section(bytes)
+                    .map(|bytes| (x.kind, Cow::Owned(bytes)))
+                    .ok_or_else(|| BinaryError::WrongFileFormat.at(x.offset).throw())
+            })
+            .try_collect()
+    }
+    /// The section in place; compressed sections have to go through [`decompress`] first.
+    #[track_caller]
+    pub(crate) fn optional_section(
+        &self,
+        kind: SectionKind,
+    ) -> Result<Option<&'a [u8]>, GenericError<BinaryError>> {
+        match self.entries.iter().find(|x| x.kind == kind) {
+            Some(x) if x.flags.contains(SectionFlags::Compressed) => {
+                Err(BinaryError::CompressedSection(kind).throw())
+            }
+            x => Ok(x.map(|x| stored(self.bytes, x))),
+        }
+    }
+    #[track_caller]
+    pub(crate) fn section(&self, kind: SectionKind) -> Result<&'a [u8], GenericError<BinaryError>> {
+        self.optional_section(kind)?
+            .ok_or(BinaryError::SectionNotFound.throw())
+    }
+}
+
+/// Expands a section stored by `compress_prepend_size`. An LZ4 block grows at most
+/// [`MAX_LZ4_RATIO`]-fold, so a larger size prefix is forged and rejected before
+/// anything is allocated for it.
+fn decompress_section(bytes: &[u8]) -> Option<Vec<u8>> {
+    let (len, block) = bytes.split_first_chunk::<4>()?;
+    let len = u32::from_le_bytes(*len) as usize;
+    if len > block.len().saturating_mul(MAX_LZ4_RATIO) {
+        return None;
+    }
+    lz4_flex::block::decompress(block, len).ok()
+}
+
+fn stored<'a>(bytes: &'a [u8], entry: &SectionEntry) -> &'a [u8] {
+    &bytes[entry.offset as usize..(entry.offset + entry.length) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attrs::{
+        ClassImplementationFlags, ConstValue, CustomAttribute, MethodImplementationFlags,
+        TypeSpecificAttr, Visibility,
+    };
+    use crate::generics::{GenericConstraintFlags, GenericParamDef, Variance};
+    use crate::instruction::StringInstruction;
+    use crate::metadata::{MethodSignature, ParamDef};
+    use crate::{StringTypeReference, core_types, fixtures};
+
+    fn sample_assembly() -> AssemblyDef {
+        let run = MethodDef::builder()
+            .name("Run(@T)")
+            .attr(MethodAttr::new(
+                Visibility::Public,
+                MethodImplementationFlags::Virtual | MethodImplementationFlags::Abstract,
+                0,
+            ))
+            .signature(
+                MethodSignature::builder()
+                    .params(vec![
+                        ParamDef::builder()
+                            .name("x")
+                            .ty(StringTypeReference::Generic(StringName::from("@T")))
+                            .build(),
+                    ])
+                    .ret_type(core_types::INT32)
+                    .build(),
+            )
+            .custom_attrs(vec![CustomAttribute::new(
+                StringTypeReference::make_static_single("App", "App.Marker"),
+                vec![ConstValue::Int32(1)],
+                Default::default(),
+            )])
+            .generic_params(vec![GenericParamDef::new(
+                StringName::from("@T"),
+                Variance::Out,
+            )])
+            .build();
+        let main = fixtures::static_method(
+            "Main()",
+            vec![
+                StringInstruction::Load_u64 {
+                    register_addr: 0,
+                    val: 42,
+                },
+                StringInstruction::ReturnVal { register_addr: 0 },
+            ],
+        );
+        let answer = FieldDef::builder()
+            .name("Answer")
+            .attr(FieldAttr::new_const(Visibility::Public))
+            .ty(core_types::UINT64)
+            .const_value(ConstValue::UInt64(42))
+            .build();
+        let base = TypeDef::builder()
+            .name("App.Base")
+            .attr(TypeAttr::new(
+                Visibility::Public,
+                TypeSpecificAttr::Class(ClassImplementationFlags::Abstract.into()),
+            ))
+            .parent(core_types::OBJECT)
+            .fields(vec![answer])
+            .methods(vec![run, main])
+            .build();
+        let empty = TypeDef::builder()
+            .name("App.Base+Empty")
+            .attr(TypeAttr::new(
+                Visibility::Private,
+                TypeSpecificAttr::Struct(Default::default()),
+            ))
+            .build();
+        AssemblyDef::builder()
+            .name("App")
+            .version(Version::new(1, 2, 0))
+            .references(vec![AssemblyReference::new(
+                StringName::from("Core"),
+                "^1.4".parse().unwrap(),
+            )])
+            .types(vec![base, empty])
+            .build()
+    }
+
+    #[test]
+    fn test_container_round_trip() {
+        let assembly = sample_assembly();
+        let bytes = encode_assembly(&assembly);
+        assert_eq!(&bytes[..4], b"PLAS");
+        assert_eq!(read_assembly(&bytes).unwrap(), assembly);
+        assert_eq!(encode_assembly(&read_assembly(&bytes).unwrap()), bytes);
+
+        let manifest = AssemblyView::parse(&bytes).unwrap().manifest().clone();
+        assert_eq!(
+            manifest.identity().to_string(),
+            format!("App 1.2.0 ({})", content_hash(&assembly))
+        );
+        assert_eq!(manifest.references(), assembly.references());
+    }
+
+    #[test]
+    fn test_container_errors() {
+        let bytes = encode_assembly(&sample_assembly());
+        let error = |bytes: &[u8]| read_assembly(bytes).unwrap_err().into_error().into_root();
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert!(matches!(error(&wrong_magic), BinaryError::WrongFileFormat));
+        assert!(matches!(
+            error(&bytes[..bytes.len() - 1]),
+            BinaryError::BinaryTooShort
+        ));
+
+        let mut sections = Encoder::default().encode(&sample_assembly());
+        sections.retain(|x| x.0 != SectionKind::Code);
+        let mut missing = Vec::new();
+        write_sections(&sections, &mut missing).unwrap();
+        assert!(matches!(error(&missing), BinaryError::SectionNotFound));
+
+        let mut sections = Encoder::default().encode(&sample_assembly());
+        sections[2].1 = 1000u32.to_le_bytes().to_vec();
+        let mut bad_name = Vec::new();
+        write_sections(&sections, &mut bad_name).unwrap();
+        assert!(matches!(
+            error(&bad_name),
+            BinaryError::StringNotFound { index: 1000 }
+        ));
+
+        let mut flagged = bytes.clone();
+        // not a `SectionFlags` bit
+        flagged[HEADER_LEN + 2] = 0x80;
+        assert!(matches!(error(&flagged), BinaryError::WrongFileFormat));
+
+        let mut conflicting = sample_assembly();
+        conflicting.types_mut()[0].methods_mut()[0].generic_params_mut()[0]
+            .set_flags(GenericConstraintFlags::Struct | GenericConstraintFlags::Class);
+        assert!(matches!(
+            error(&encode_assembly(&conflicting)),
+            BinaryError::InvalidAttr(_)
+        ));
+        let mut struct_with_base = GenericParamDef::new(StringName::from("@T"), Variance::In);
+        struct_with_base
+            .set_flags(GenericConstraintFlags::Struct.into())
+            .set_base_class(Some(core_types::OBJECT));
+        let mut conflicting = sample_assembly();
+        conflicting.types_mut()[1]
+            .generic_params_mut()
+            .push(struct_with_base);
+        assert!(matches!(
+            error(&encode_assembly(&conflicting)),
+            BinaryError::InvalidAttr(_)
+        ));
+    }
+
+    #[test]
+    fn test_container_integrity() {
+        let assembly = sample_assembly();
+        let bytes = encode_assembly(&assembly);
+        let view = AssemblyView::parse(&bytes).unwrap();
+        let hash = view.verify().unwrap();
+        assert_eq!(hash, content_hash(&assembly));
+        assert_eq!(view.content_hash(), Some(hash));
+        assert_eq!(&bytes[8..HEADER_LEN], hash.as_bytes());
+        assert_eq!(hash.to_string().len(), 64);
+
+        let mut renamed = assembly.clone();
+        *renamed.name_mut() = StringName::from("Other");
+        assert_ne!(content_hash(&renamed), hash);
+
+        let error = |bytes: &[u8]| read_assembly(bytes).unwrap_err().into_error().into_root();
+        let mut corrupted = bytes.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            error(&corrupted),
+            BinaryError::ChecksumMismatch(SectionKind::Manifest)
+        ));
+        // only checked on request
+        let view = AssemblyView::parse(&corrupted).unwrap();
+        assert!(view.verify().is_err());
+        let mut wrong_hash = bytes.clone();
+        wrong_hash[8] ^= 1;
+        assert!(matches!(
+            error(&wrong_hash),
+            BinaryError::ContentHashMismatch
+        ));
+
+        // version 1: no hash in the header and no checksums in the directory
+        let mut v1 = Vec::new();
+        write_sections_as(
+            1,
+            &Encoder::default().encode(&assembly),
+            WriteOptions::default(),
+            &mut v1,
+        )
+        .unwrap();
+        assert_eq!(read_assembly(&v1).unwrap(), assembly);
+        let view = AssemblyView::parse(&v1).unwrap();
+        assert_eq!(view.content_hash(), None);
+        assert_eq!(view.verify().unwrap(), hash);
+    }
+
+    #[test]
+    fn test_container_compression() {
+        let assembly = sample_assembly();
+        let plain = encode_assembly(&assembly);
+        let options = WriteOptions::builder().compress_sections(true).build();
+        let mut compressed = Vec::new();
+        write_assembly_with(&assembly, options, &mut compressed).unwrap();
+        assert!(compressed.len() < plain.len());
+
+        let directory = Directory::parse(&compressed).unwrap();
+        assert!(directory.is_compressed());
+        assert_eq!(directory.verify().unwrap(), content_hash(&assembly));
+        assert!(matches!(
+            AssemblyView::parse(&compressed)
+                .err()
+                .unwrap()
+                .into_error()
+                .into_root(),
+            BinaryError::CompressedSection(_)
+        ));
+
+        assert_eq!(read_assembly(&compressed).unwrap(), assembly);
+        assert_eq!(decompress(&compressed).unwrap().as_ref(), plain.as_slice());
+        assert!(matches!(decompress(&plain).unwrap(), Cow::Borrowed(_)));
+
+        // a size prefix far beyond what the block can expand to
+        let mut forged = compressed.clone();
+        let entry = directory
+            .entries()
+            .iter()
+            .find(|x| x.flags().contains(SectionFlags::Compressed))
+            .unwrap();
+        let at = entry.offset() as usize;
+        forged[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            decompress(&forged).unwrap_err().into_error().into_root(),
+            BinaryError::WrongFileFormat
+        ));
+    }
+}
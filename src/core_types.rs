@@ -0,0 +1,191 @@
+//! Well-known types of the core (`!`) assembly.
+
+use crate::{StringName, StringTypeReference};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+macro core_types($all:ident; $($name:ident => $ty:literal),* $(,)?) {
+    $(
+        pub const $name: StringTypeReference = StringTypeReference::core_static_single_type($ty);
+    )*
+
+    /// Every type in this catalog.
+    pub static $all: [StringTypeReference; ${count($name)}] = [$($name),*];
+}
+
+core_types! {
+    ALL;
+
+    VOID => "System.Void",
+    BOOLEAN => "System.Boolean",
+    CHAR => "System.Char",
+    UINT8 => "System.UInt8",
+    INT8 => "System.Int8",
+    UINT16 => "System.UInt16",
+    INT16 => "System.Int16",
+    UINT32 => "System.UInt32",
+    INT32 => "System.Int32",
+    UINT64 => "System.UInt64",
+    INT64 => "System.Int64",
+    USIZE => "System.USize",
+    ISIZE => "System.ISize",
+    FLOAT32 => "System.Float32",
+    FLOAT64 => "System.Float64",
+
+    OBJECT => "System.Object",
+    STRING => "System.String",
+    ARRAY => "System.Array",
+    TYPE => "System.Type",
+
+    EXCEPTION => "System.Exception",
+    ARGUMENT_EXCEPTION => "System.ArgumentException",
+    ARGUMENT_NULL_EXCEPTION => "System.ArgumentNullException",
+    INDEX_OUT_OF_RANGE_EXCEPTION => "System.IndexOutOfRangeException",
+    INVALID_CAST_EXCEPTION => "System.InvalidCastException",
+    INVALID_OPERATION_EXCEPTION => "System.InvalidOperationException",
+    NOT_SUPPORTED_EXCEPTION => "System.NotSupportedException",
+    NULL_REFERENCE_EXCEPTION => "System.NullReferenceException",
+    OVERFLOW_EXCEPTION => "System.OverflowException",
+    DIVIDE_BY_ZERO_EXCEPTION => "System.DivideByZeroException",
+
+    CONSOLE => "System.Console",
+    CONSOLE_COLOR => "System.ConsoleColor",
+    CONSOLE_KEY => "System.ConsoleKey",
+    CONSOLE_KEY_INFO => "System.ConsoleKeyInfo",
+}
+
+/// Looks up a core type by its name without assembly, e.g. `System.UInt64`.
+pub fn by_name(name: &str) -> Option<&'static StringTypeReference> {
+    ALL.iter().find(|x| *x.unwrap_single_name_ref() == *name)
+}
+
+pub fn is_core_type(ty: &StringTypeReference) -> bool {
+    ALL.contains(ty)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum PrimitiveType {
+    Void,
+    Boolean,
+    Char,
+    UInt8,
+    Int8,
+    UInt16,
+    Int16,
+    UInt32,
+    Int32,
+    UInt64,
+    Int64,
+    USize,
+    ISize,
+    Float32,
+    Float64,
+}
+
+impl PrimitiveType {
+    pub const fn type_ref(self) -> StringTypeReference {
+        match self {
+            Self::Void => VOID,
+            Self::Boolean => BOOLEAN,
+            Self::Char => CHAR,
+            Self::UInt8 => UINT8,
+            Self::Int8 => INT8,
+            Self::UInt16 => UINT16,
+            Self::Int16 => INT16,
+            Self::UInt32 => UINT32,
+            Self::Int32 => INT32,
+            Self::UInt64 => UINT64,
+            Self::Int64 => INT64,
+            Self::USize => USIZE,
+            Self::ISize => ISIZE,
+            Self::Float32 => FLOAT32,
+            Self::Float64 => FLOAT64,
+        }
+    }
+
+    pub fn from_type_ref(ty: &StringTypeReference) -> Option<Self> {
+        (0..=Self::Float64 as u8)
+            .map(|x| Self::try_from(x).unwrap())
+            .find(|x| x.type_ref() == *ty)
+    }
+
+    /// Size in bytes of the value, `None` for `Void`.
+    pub const fn size(self) -> Option<usize> {
+        match self {
+            Self::Void => None,
+            Self::Boolean | Self::UInt8 | Self::Int8 => Some(1),
+            Self::UInt16 | Self::Int16 => Some(2),
+            Self::Char | Self::UInt32 | Self::Int32 | Self::Float32 => Some(4),
+            Self::UInt64 | Self::Int64 | Self::USize | Self::ISize | Self::Float64 => Some(8),
+        }
+    }
+}
+
+/// Rust types with a counterpart in the core assembly.
+pub trait CoreType {
+    const TYPE_REF: StringTypeReference;
+}
+
+/// Rust primitives with a [`PrimitiveType`] counterpart.
+pub trait CorePrimitive: CoreType {
+    const PRIMITIVE: PrimitiveType;
+}
+
+macro impl_core_primitive($($t:ty => $p:ident),* $(,)?) {
+    $(
+        impl CoreType for $t {
+            const TYPE_REF: StringTypeReference = PrimitiveType::$p.type_ref();
+        }
+        impl CorePrimitive for $t {
+            const PRIMITIVE: PrimitiveType = PrimitiveType::$p;
+        }
+    )*
+}
+
+impl_core_primitive! {
+    () => Void,
+    bool => Boolean,
+    char => Char,
+    u8 => UInt8,
+    i8 => Int8,
+    u16 => UInt16,
+    i16 => Int16,
+    u32 => UInt32,
+    i32 => Int32,
+    u64 => UInt64,
+    i64 => Int64,
+    usize => USize,
+    isize => ISize,
+    f32 => Float32,
+    f64 => Float64,
+}
+
+impl CoreType for str {
+    const TYPE_REF: StringTypeReference = STRING;
+}
+
+impl CoreType for String {
+    const TYPE_REF: StringTypeReference = STRING;
+}
+
+impl CoreType for StringName {
+    const TYPE_REF: StringTypeReference = STRING;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primitive_lookup() {
+        assert_eq!(u8::TYPE_REF, UINT8);
+        assert_eq!(UINT8.string_name_repr().as_str(), "[!]System.UInt8");
+        assert_eq!(
+            PrimitiveType::from_type_ref(&u64::TYPE_REF),
+            Some(PrimitiveType::UInt64)
+        );
+        assert_eq!(PrimitiveType::from_type_ref(&STRING), None);
+        assert_eq!(by_name("System.Object"), Some(&OBJECT));
+        assert!(is_core_type(&CONSOLE_KEY_INFO));
+    }
+}
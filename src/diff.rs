@@ -0,0 +1,698 @@
+//! Structural comparison of two versions of an assembly.
+//!
+//! Types are matched by name, and fields and methods by name within their type. A method
+//! name embeds its parameter types, so a changed parameter list shows up as one method
+//! removed and another added. Every change is classified as breaking or not for code
+//! compiled against the old version; only what is reachable from other assemblies, see
+//! [`Visibility::is_visible_outside_assembly`], can break them.
+
+use crate::StringName;
+use crate::StringTypeReference;
+use crate::attrs::{
+    ClassImplementationFlags, ConstValue, FieldImplementationFlags, MethodImplementationFlags,
+    TypeAttr, TypeSpecificAttr, Visibility,
+};
+use crate::errors::{CompatibilityError, GenericError};
+use crate::generics::HasGenericParams;
+use crate::instruction::StringInstruction;
+use crate::metadata::{AssemblyDef, FieldDef, MethodDef, ParamDef, TypeDef};
+use enumflags2::BitFlags;
+use getset::{CopyGetters, Getters};
+use semver::{Comparator, Op, VersionReq};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Member {
+    Type,
+    Field(StringName),
+    Method(StringName),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Visibility {
+        old: Visibility,
+        new: Visibility,
+    },
+    TypeKind {
+        old: TypeSpecificAttr,
+        new: TypeSpecificAttr,
+    },
+    Parent {
+        old: Option<StringTypeReference>,
+        new: Option<StringTypeReference>,
+    },
+    InterfaceAdded(StringTypeReference),
+    InterfaceRemoved(StringTypeReference),
+    /// Of the type or method.
+    GenericParams,
+    /// Names or types of the method parameters.
+    Params,
+    FieldFlags {
+        old: BitFlags<FieldImplementationFlags>,
+        new: BitFlags<FieldImplementationFlags>,
+    },
+    FieldType {
+        old: StringTypeReference,
+        new: StringTypeReference,
+    },
+    ConstValue {
+        old: Option<ConstValue>,
+        new: Option<ConstValue>,
+    },
+    MethodFlags {
+        old: BitFlags<MethodImplementationFlags>,
+        new: BitFlags<MethodImplementationFlags>,
+    },
+    ReturnType {
+        old: StringTypeReference,
+        new: StringTypeReference,
+    },
+    Body(Vec<BodyEdit>),
+}
+
+/// One step of turning the old body into the new one. Indices are into the respective
+/// body.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BodyEdit {
+    Removed {
+        old_index: usize,
+        instruction: StringInstruction,
+    },
+    Added {
+        new_index: usize,
+        instruction: StringInstruction,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Getters, CopyGetters)]
+pub struct Change {
+    #[getset(get = "pub")]
+    ty: StringName,
+    #[getset(get = "pub")]
+    member: Member,
+    #[getset(get = "pub")]
+    kind: ChangeKind,
+    #[getset(get_copy = "pub")]
+    breaking: bool,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.member {
+            Member::Type => write!(f, "{}", self.ty)?,
+            Member::Field(name) => write!(f, "{}.{name}", self.ty)?,
+            Member::Method(name) => write!(f, "{}::{name}", self.ty)?,
+        }
+        match &self.kind {
+            ChangeKind::Added => write!(f, " added"),
+            ChangeKind::Removed => write!(f, " removed"),
+            ChangeKind::Visibility { old, new } => write!(f, " visibility {old:?} -> {new:?}"),
+            ChangeKind::TypeKind { old, new } => write!(f, " kind {old:?} -> {new:?}"),
+            ChangeKind::Parent { old, new } => write!(f, " parent {old:?} -> {new:?}"),
+            ChangeKind::InterfaceAdded(ty) => write!(f, " implements {ty}"),
+            ChangeKind::InterfaceRemoved(ty) => write!(f, " no longer implements {ty}"),
+            ChangeKind::GenericParams => write!(f, " generic parameters changed"),
+            ChangeKind::Params => write!(f, " parameters changed"),
+            ChangeKind::FieldFlags { old, new } => write!(
+                f,
+                " flags {:?} -> {:?}",
+                old.iter().collect::<Vec<_>>(),
+                new.iter().collect::<Vec<_>>()
+            ),
+            ChangeKind::FieldType { old, new } => write!(f, " type {old} -> {new}"),
+            ChangeKind::ConstValue { old, new } => write!(f, " value {old:?} -> {new:?}"),
+            ChangeKind::MethodFlags { old, new } => write!(
+                f,
+                " flags {:?} -> {:?}",
+                old.iter().collect::<Vec<_>>(),
+                new.iter().collect::<Vec<_>>()
+            ),
+            ChangeKind::ReturnType { old, new } => write!(f, " return type {old} -> {new}"),
+            ChangeKind::Body(edits) => write!(f, " body changed ({} edits)", edits.len()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct AssemblyDiff {
+    changes: Vec<Change>,
+}
+
+impl AssemblyDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(|x| x.breaking)
+    }
+    pub fn breaking(&self) -> impl Iterator<Item = &Change> {
+        self.changes.iter().filter(|x| x.breaking)
+    }
+}
+
+impl Display for AssemblyDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            let tag = match change.breaking {
+                true => "breaking",
+                false => "compatible",
+            };
+            writeln!(f, "{tag}: {change}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the metadata and method bodies of `old` and `new`, in the order of `old`
+/// followed by what only `new` has.
+pub fn diff_assemblies(old: &AssemblyDef, new: &AssemblyDef) -> AssemblyDiff {
+    let mut differ = Differ::default();
+    for old_ty in old.types() {
+        match new.find_type(old_ty.name()) {
+            Some(new_ty) => differ.ty(old_ty, new_ty),
+            None => differ.push(
+                old_ty,
+                Member::Type,
+                ChangeKind::Removed,
+                is_public(old_ty.attr().vis()),
+            ),
+        }
+    }
+    for new_ty in new.types() {
+        if old.find_type(new_ty.name()).is_none() {
+            differ.push(new_ty, Member::Type, ChangeKind::Added, false);
+        }
+    }
+    AssemblyDiff {
+        changes: differ.changes,
+    }
+}
+
+/// Fails if `new` has breaking changes against `old` although its version still satisfies
+/// `^old.version()`, i.e. claims to be compatible, and if `new` has a lower version than
+/// `old`.
+#[track_caller]
+pub fn check_compatible(
+    old: &AssemblyDef,
+    new: &AssemblyDef,
+) -> Result<AssemblyDiff, GenericError<CompatibilityError>> {
+    let old_version = old.version();
+    if new.version() < old_version {
+        return Err(CompatibilityError::Downgrade {
+            old: old_version.clone(),
+            new: new.version().clone(),
+        }
+        .throw());
+    }
+    let diff = diff_assemblies(old, new);
+    let req = VersionReq {
+        comparators: vec![Comparator {
+            op: Op::Caret,
+            major: old_version.major,
+            minor: Some(old_version.minor),
+            patch: Some(old_version.patch),
+            pre: old_version.pre.clone(),
+        }],
+    };
+    if diff.is_breaking() && req.matches(new.version()) {
+        return Err(CompatibilityError::BreakingChanges {
+            version: new.version().clone(),
+            changes: diff.breaking().cloned().collect(),
+        }
+        .throw());
+    }
+    Ok(diff)
+}
+
+fn is_public(vis: Visibility) -> bool {
+    vis.is_visible_outside_assembly()
+}
+
+fn narrows(old: Visibility, new: Visibility) -> bool {
+    is_public(old) && (!is_public(new) || (old == Visibility::Public && new != Visibility::Public))
+}
+
+fn type_kind_breaks(old: &TypeAttr, new: &TypeAttr) -> bool {
+    use ClassImplementationFlags::*;
+    let class_flags = |attr: &TypeAttr| match attr.specific() {
+        TypeSpecificAttr::Class(flags) => flags & (Static | Record),
+        _ => BitFlags::empty(),
+    };
+    std::mem::discriminant(&old.specific()) != std::mem::discriminant(&new.specific())
+        || (!old.is_abstract() && new.is_abstract())
+        || (!old.is_sealed() && new.is_sealed())
+        || old.is_record() != new.is_record()
+        || class_flags(old) != class_flags(new)
+}
+
+fn field_flags_break(
+    old: BitFlags<FieldImplementationFlags>,
+    new: BitFlags<FieldImplementationFlags>,
+) -> bool {
+    use FieldImplementationFlags::*;
+    let changed = old ^ new;
+    changed.intersects(Static | Const) || (new & !old).contains(ReadOnly)
+}
+
+fn method_flags_break(
+    old: BitFlags<MethodImplementationFlags>,
+    new: BitFlags<MethodImplementationFlags>,
+) -> bool {
+    use MethodImplementationFlags::*;
+    let changed = old ^ new;
+    changed.intersects(Static | Constructor | Generic)
+        || (new & !old).intersects(Abstract | Sealed)
+        || (old & !new).contains(Virtual)
+}
+
+#[derive(Default)]
+struct Differ {
+    changes: Vec<Change>,
+}
+
+impl Differ {
+    fn push(&mut self, ty: &TypeDef, member: Member, kind: ChangeKind, breaking: bool) {
+        self.changes.push(Change {
+            ty: ty.name().clone(),
+            member,
+            kind,
+            breaking,
+        });
+    }
+
+    fn ty(&mut self, old: &TypeDef, new: &TypeDef) {
+        let visible = is_public(old.attr().vis());
+        let (old_vis, new_vis) = (old.attr().vis(), new.attr().vis());
+        if old_vis != new_vis {
+            let kind = ChangeKind::Visibility {
+                old: old_vis,
+                new: new_vis,
+            };
+            self.push(new, Member::Type, kind, narrows(old_vis, new_vis));
+        }
+        if old.attr().specific() != new.attr().specific() {
+            let kind = ChangeKind::TypeKind {
+                old: old.attr().specific(),
+                new: new.attr().specific(),
+            };
+            let breaking = visible && type_kind_breaks(old.attr(), new.attr());
+            self.push(new, Member::Type, kind, breaking);
+        }
+        if old.parent() != new.parent() {
+            let kind = ChangeKind::Parent {
+                old: old.parent().clone(),
+                new: new.parent().clone(),
+            };
+            self.push(new, Member::Type, kind, visible);
+        }
+        for interface in old.interfaces() {
+            if !new.interfaces().contains(interface) {
+                let kind = ChangeKind::InterfaceRemoved(interface.clone());
+                self.push(new, Member::Type, kind, visible);
+            }
+        }
+        for interface in new.interfaces() {
+            if !old.interfaces().contains(interface) {
+                let kind = ChangeKind::InterfaceAdded(interface.clone());
+                self.push(new, Member::Type, kind, false);
+            }
+        }
+        if old.generic_params() != new.generic_params() {
+            self.push(new, Member::Type, ChangeKind::GenericParams, visible);
+        }
+
+        for old_field in old.fields() {
+            let member = Member::Field(old_field.name().clone());
+            match new.field(old_field.name()) {
+                Some(new_field) => self.field(new, visible, old_field, new_field),
+                None => {
+                    let breaking = visible && is_public(old_field.attr().vis());
+                    self.push(new, member, ChangeKind::Removed, breaking);
+                }
+            }
+        }
+        for new_field in new.fields() {
+            if old.field(new_field.name()).is_none() {
+                let member = Member::Field(new_field.name().clone());
+                self.push(new, member, ChangeKind::Added, false);
+            }
+        }
+
+        for old_method in old.methods() {
+            let member = Member::Method(old_method.name().clone());
+            match new.method(old_method.name()) {
+                Some(new_method) => self.method(new, visible, old_method, new_method),
+                None => {
+                    let breaking = visible && is_public(old_method.attr().vis());
+                    self.push(new, member, ChangeKind::Removed, breaking);
+                }
+            }
+        }
+        for new_method in new.methods() {
+            if old.method(new_method.name()).is_none() {
+                // code outside the assembly that derives from the type has to implement it
+                let breaking = visible
+                    && !new.attr().is_sealed()
+                    && new_method
+                        .attr()
+                        .impl_flags()
+                        .contains(MethodImplementationFlags::Abstract);
+                let member = Member::Method(new_method.name().clone());
+                self.push(new, member, ChangeKind::Added, breaking);
+            }
+        }
+    }
+
+    fn field(&mut self, ty: &TypeDef, type_visible: bool, old: &FieldDef, new: &FieldDef) {
+        let member = || Member::Field(old.name().clone());
+        let (old_attr, new_attr) = (old.attr(), new.attr());
+        let visible = type_visible && is_public(old_attr.vis());
+        if old_attr.vis() != new_attr.vis() {
+            let kind = ChangeKind::Visibility {
+                old: old_attr.vis(),
+                new: new_attr.vis(),
+            };
+            let breaking = type_visible && narrows(old_attr.vis(), new_attr.vis());
+            self.push(ty, member(), kind, breaking);
+        }
+        if old_attr.impl_flags() != new_attr.impl_flags() {
+            let kind = ChangeKind::FieldFlags {
+                old: old_attr.impl_flags(),
+                new: new_attr.impl_flags(),
+            };
+            let breaking =
+                visible && field_flags_break(old_attr.impl_flags(), new_attr.impl_flags());
+            self.push(ty, member(), kind, breaking);
+        }
+        if old.ty() != new.ty() {
+            let kind = ChangeKind::FieldType {
+                old: old.ty().clone(),
+                new: new.ty().clone(),
+            };
+            self.push(ty, member(), kind, visible);
+        }
+        if old.const_value() != new.const_value() {
+            // constants are copied into the code that reads them
+            let kind = ChangeKind::ConstValue {
+                old: old.const_value().clone(),
+                new: new.const_value().clone(),
+            };
+            self.push(ty, member(), kind, visible);
+        }
+    }
+
+    fn method(&mut self, ty: &TypeDef, type_visible: bool, old: &MethodDef, new: &MethodDef) {
+        let member = || Member::Method(old.name().clone());
+        let (old_attr, new_attr) = (old.attr(), new.attr());
+        let visible = type_visible && is_public(old_attr.vis());
+        if old_attr.vis() != new_attr.vis() {
+            let kind = ChangeKind::Visibility {
+                old: old_attr.vis(),
+                new: new_attr.vis(),
+            };
+            let breaking = type_visible && narrows(old_attr.vis(), new_attr.vis());
+            self.push(ty, member(), kind, breaking);
+        }
+        if old_attr.impl_flags() != new_attr.impl_flags() {
+            let kind = ChangeKind::MethodFlags {
+                old: old_attr.impl_flags(),
+                new: new_attr.impl_flags(),
+            };
+            let breaking =
+                visible && method_flags_break(old_attr.impl_flags(), new_attr.impl_flags());
+            self.push(ty, member(), kind, breaking);
+        }
+        let (old_params, new_params) = (old.signature().params(), new.signature().params());
+        if old_params != new_params {
+            // callers only depend on the types; names may change freely
+            let retyped = !old_params
+                .iter()
+                .map(ParamDef::ty)
+                .eq(new_params.iter().map(ParamDef::ty));
+            self.push(ty, member(), ChangeKind::Params, visible && retyped);
+        }
+        if old.signature().ret_type() != new.signature().ret_type() {
+            let kind = ChangeKind::ReturnType {
+                old: old.signature().ret_type().clone(),
+                new: new.signature().ret_type().clone(),
+            };
+            self.push(ty, member(), kind, visible);
+        }
+        if old.generic_params() != new.generic_params() {
+            self.push(ty, member(), ChangeKind::GenericParams, visible);
+        }
+        if old.body() != new.body() {
+            let kind = ChangeKind::Body(diff_bodies(old.body(), new.body()));
+            self.push(ty, member(), kind, false);
+        }
+    }
+}
+
+/// Above this many cells for the LCS table, the differing middle of two bodies is reported
+/// as removed and added as a whole.
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+/// An edit script between two bodies: the shortest one, from their longest common
+/// subsequence, unless the part between the common prefix and suffix is too large.
+fn diff_bodies(old: &[StringInstruction], new: &[StringInstruction]) -> Vec<BodyEdit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old_rest[..old_rest.len() - suffix];
+    let new_mid = &new_rest[..new_rest.len() - suffix];
+    let removed = |i: usize| BodyEdit::Removed {
+        old_index: prefix + i,
+        instruction: old_mid[i].clone(),
+    };
+    let added = |j: usize| BodyEdit::Added {
+        new_index: prefix + j,
+        instruction: new_mid[j].clone(),
+    };
+    if (old_mid.len() + 1).saturating_mul(new_mid.len() + 1) > MAX_LCS_CELLS {
+        return (0..old_mid.len())
+            .map(removed)
+            .chain((0..new_mid.len()).map(added))
+            .collect();
+    }
+
+    // lcs[i][j] is the length of the LCS of old_mid[i..] and new_mid[j..]
+    let mut lcs = vec![vec![0usize; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i][j] = match old_mid[i] == new_mid[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            i += 1;
+            j += 1;
+        } else if j < new_mid.len() && (i == old_mid.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            edits.push(added(j));
+            j += 1;
+        } else {
+            edits.push(removed(i));
+            i += 1;
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attrs::{FieldAttr, InterfaceImplementationFlags};
+    use crate::{core_types, fixtures};
+    use semver::Version;
+
+    fn method(name: &str, vis: Visibility, body: Vec<StringInstruction>) -> MethodDef {
+        let mut method = fixtures::static_method(name, body);
+        method.attr_mut().set_vis(vis);
+        method
+    }
+
+    fn assembly(version: Version, types: Vec<TypeDef>) -> AssemblyDef {
+        AssemblyDef::builder()
+            .name("Lib")
+            .version(version)
+            .types(types)
+            .build()
+    }
+
+    #[test]
+    fn test_diff() {
+        let load = |val| StringInstruction::Load_u64 {
+            register_addr: 0,
+            val,
+        };
+        let ret = StringInstruction::ReturnVal { register_addr: 0 };
+        let field = |vis, value| {
+            FieldDef::builder()
+                .name("Limit")
+                .attr(FieldAttr::new_const(vis))
+                .ty(core_types::UINT64)
+                .const_value(ConstValue::UInt64(value))
+                .build()
+        };
+        let class = |fields, methods| {
+            TypeDef::builder()
+                .name("Lib.Util")
+                .attr(TypeAttr::new(
+                    Visibility::Public,
+                    TypeSpecificAttr::Class(BitFlags::empty()),
+                ))
+                .parent(core_types::OBJECT)
+                .fields(fields)
+                .methods(methods)
+                .build()
+        };
+        let interface = |methods| {
+            TypeDef::builder()
+                .name("Lib.IShape")
+                .attr(TypeAttr::new(
+                    Visibility::Public,
+                    TypeSpecificAttr::Interface(InterfaceImplementationFlags::Sealed.into()),
+                ))
+                .methods(methods)
+                .build()
+        };
+        let mut area = method("Area()", Visibility::Public, vec![]);
+        area.attr_mut().set_impl_flags(
+            MethodImplementationFlags::Abstract | MethodImplementationFlags::Virtual,
+        );
+
+        let old = assembly(
+            Version::new(1, 2, 0),
+            vec![
+                class(
+                    vec![field(Visibility::Public, 10)],
+                    vec![
+                        method("Get()", Visibility::Public, vec![load(1), ret.clone()]),
+                        method("Helper()", Visibility::AssemblyOnly, vec![]),
+                        method("Old()", Visibility::Public, vec![]),
+                    ],
+                ),
+                interface(vec![]),
+            ],
+        );
+        let new = assembly(
+            Version::new(1, 3, 0),
+            vec![
+                class(
+                    vec![field(Visibility::Public, 10)],
+                    vec![
+                        method("Get()", Visibility::Public, vec![load(2), ret.clone()]),
+                        method("New()", Visibility::Public, vec![]),
+                    ],
+                ),
+                interface(vec![area.clone()]),
+            ],
+        );
+        let diff = diff_assemblies(&old, &new);
+        assert_eq!(
+            diff.to_string(),
+            "compatible: Lib.Util::Get() body changed (2 edits)\n\
+             compatible: Lib.Util::Helper() removed\n\
+             breaking: Lib.Util::Old() removed\n\
+             compatible: Lib.Util::New() added\n\
+             compatible: Lib.IShape::Area() added\n"
+        );
+        assert_eq!(
+            diff.changes()[0].kind(),
+            &ChangeKind::Body(vec![
+                BodyEdit::Added {
+                    new_index: 0,
+                    instruction: load(2),
+                },
+                BodyEdit::Removed {
+                    old_index: 0,
+                    instruction: load(1),
+                },
+            ])
+        );
+        let e = check_compatible(&old, &new).unwrap_err().into_error();
+        assert_eq!(
+            e.to_string(),
+            "BreakingChanges(1.3.0: Lib.Util::Old() removed)"
+        );
+        let mut major = new.clone();
+        *major.version_mut() = Version::new(2, 0, 0);
+        assert!(check_compatible(&old, &major).is_ok());
+        assert!(matches!(
+            check_compatible(&major, &old).unwrap_err().into_error(),
+            CompatibilityError::Downgrade { .. }
+        ));
+
+        // bodies differing past the LCS budget are replaced as a whole
+        let long = |offset: u64| (0..1100).map(|x| load(offset + x)).collect::<Vec<_>>();
+        let mut old_body = long(0);
+        old_body.push(ret.clone());
+        let edits = diff_bodies(&old_body, &long(5000));
+        assert_eq!(edits.len(), 2201);
+        assert!(matches!(edits[0], BodyEdit::Removed { old_index: 0, .. }));
+        assert!(matches!(
+            edits[2200],
+            BodyEdit::Added {
+                new_index: 1099,
+                ..
+            }
+        ));
+
+        // the interface can now be implemented elsewhere, and the constant is inlined there
+        let mut newer = new.clone();
+        newer.types_mut()[0].fields_mut()[0] = field(Visibility::Public, 20);
+        newer.types_mut()[1] = TypeDef::builder()
+            .name("Lib.IShape")
+            .attr(TypeAttr::new(
+                Visibility::Public,
+                TypeSpecificAttr::Interface(BitFlags::empty()),
+            ))
+            .methods(vec![area])
+            .build();
+        let param = |name: &str| {
+            ParamDef::builder()
+                .name(name)
+                .ty(core_types::UINT64)
+                .build()
+        };
+        newer.types_mut()[0].methods_mut()[0]
+            .signature_mut()
+            .params_mut()
+            .push(param("x"));
+        // callers keep working when only a name changes
+        let mut renamed = newer.clone();
+        renamed.types_mut()[0].methods_mut()[0]
+            .signature_mut()
+            .params_mut()[0] = param("y");
+        let diff = diff_assemblies(&newer, &renamed);
+        assert_eq!(
+            diff.to_string(),
+            "compatible: Lib.Util::Get() parameters changed\n"
+        );
+        let diff = diff_assemblies(&old, &newer);
+        let breaking = diff.breaking().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            breaking,
+            [
+                "Lib.Util.Limit value Some(UInt64(10)) -> Some(UInt64(20))",
+                "Lib.Util::Get() parameters changed",
+                "Lib.Util::Old() removed",
+                "Lib.IShape::Area() added",
+            ]
+        );
+        assert!(diff_assemblies(&new, &new).is_empty());
+    }
+}
@@ -1,6 +1,10 @@
 use derive_more::Display;
 use std::panic::Location;
 
+use crate::container::SectionKind;
+use crate::diff::Change;
+use crate::linker::DuplicateType;
+use crate::manifest::Requirement;
 use crate::{StringMethodReference, StringName, StringTypeReference};
 
 pub enum TypeSystemError {}
@@ -86,6 +90,30 @@ pub enum BinaryError {
     SectionNotFound,
     BinaryTooShort,
     EnumOutOfBounds(&'static str),
+    #[display("InvalidAttr({_0})")]
+    InvalidAttr(AttrError),
+    #[display("Io({_0})")]
+    Io(std::io::ErrorKind),
+    /// The stored CRC32 of a section does not match its content.
+    #[display("ChecksumMismatch({_0:?})")]
+    ChecksumMismatch(SectionKind),
+    ContentHashMismatch,
+    /// Read in place although compressed; see [`crate::container::decompress`].
+    #[display("CompressedSection({_0:?})")]
+    CompressedSection(SectionKind),
+    /// There is no format version after this one to migrate to.
+    #[display("NoMigrationFrom(format version {_0})")]
+    NoMigrationFrom(u16),
+    #[display("UnsupportedVersion(format version {found} is newer than the supported {supported})")]
+    UnsupportedVersion {
+        found: u16,
+        supported: u16,
+    },
+    #[display("{error} at offset {offset}")]
+    AtOffset {
+        offset: u64,
+        error: Box<BinaryError>,
+    },
 }
 
 impl BinaryError {
@@ -94,6 +122,29 @@ impl BinaryError {
     pub fn throw(self) -> GenericError<Self> {
         GenericError::throw(self)
     }
+    /// Locates the error at `offset` in the input, keeping the innermost offset.
+    pub fn at(self, offset: u64) -> Self {
+        match self {
+            Self::AtOffset { .. } => self,
+            _ => Self::AtOffset {
+                offset,
+                error: Box::new(self),
+            },
+        }
+    }
+    /// The error without its location.
+    pub fn root(&self) -> &Self {
+        match self {
+            Self::AtOffset { error, .. } => error.root(),
+            _ => self,
+        }
+    }
+    pub fn into_root(self) -> Self {
+        match self {
+            Self::AtOffset { error, .. } => error.into_root(),
+            _ => self,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Display)]
@@ -112,6 +163,15 @@ impl<E: std::error::Error + 'static> GenericError<E> {
             caller: Location::caller(),
         }
     }
+    pub fn error(&self) -> &E {
+        &self.e
+    }
+    pub fn caller(&self) -> &'static Location<'static> {
+        self.caller
+    }
+    pub fn into_error(self) -> E {
+        self.e
+    }
 }
 
 impl<E: std::error::Error + 'static> std::error::Error for GenericError<E> {}
@@ -127,6 +187,7 @@ pub use anyhow::anyhow;
 pub enum ParseStrError {
     AtStringTypeReference(StringName),
     AtStringMethodReference(StringName),
+    AtMangledName(StringName),
 }
 
 impl ParseStrError {
@@ -136,7 +197,165 @@ impl ParseStrError {
     }
 }
 
+#[derive(Clone, Debug, Display, thiserror::Error)]
+pub enum AttrError {
+    #[display("ConflictingFlags({_0}, {_1})")]
+    ConflictingFlags(String, String),
+    #[display("MissingFlag({flag} required by {required_by})")]
+    MissingFlag {
+        flag: String,
+        required_by: String,
+    },
+    #[display("UnexpectedBody({_0})")]
+    UnexpectedBody(String),
+    MissingBody,
+    MissingConstValue,
+    UnexpectedConstValue,
+}
+
+impl AttrError {
+    #[track_caller]
+    pub fn throw(self) -> GenericError<Self> {
+        GenericError::throw(self)
+    }
+}
+
+/// Why a `type_vars` map does not satisfy the generic parameters it instantiates.
+#[derive(Clone, Debug, Display, thiserror::Error)]
+pub enum MakeGenericError {
+    #[display("MissingTypeVar({_0})")]
+    MissingTypeVar(StringName),
+    #[display("UnknownTypeVar({_0})")]
+    UnknownTypeVar(StringName),
+    #[display("NotValueType({param}: {got})")]
+    NotValueType {
+        param: StringName,
+        got: Box<StringTypeReference>,
+    },
+    #[display("NotReferenceType({param}: {got})")]
+    NotReferenceType {
+        param: StringName,
+        got: Box<StringTypeReference>,
+    },
+    #[display("NoDefaultCtor({param}: {got})")]
+    NoDefaultCtor {
+        param: StringName,
+        got: Box<StringTypeReference>,
+    },
+    #[display("NotSubclass({param}: {got} does not derive from {expected})")]
+    NotSubclass {
+        param: StringName,
+        got: Box<StringTypeReference>,
+        expected: Box<StringTypeReference>,
+    },
+    #[display("NotImplemented({param}: {got} does not implement {interface})")]
+    NotImplemented {
+        param: StringName,
+        got: Box<StringTypeReference>,
+        interface: Box<StringTypeReference>,
+    },
+}
+
+impl MakeGenericError {
+    #[track_caller]
+    pub fn throw(self) -> GenericError<Self> {
+        GenericError::throw(self)
+    }
+}
+
 #[derive(Clone, Debug, Display, thiserror::Error)]
 pub enum CompileServiceError {
     NoCompilerMatched(StringName),
 }
+
+/// Why no version of an assembly satisfies the requirements on it.
+#[derive(Clone, Debug, Display, thiserror::Error)]
+pub enum ResolveError {
+    #[display("NotFound({name}: {})", display_list(required_by))]
+    NotFound {
+        name: StringName,
+        required_by: Vec<Requirement>,
+    },
+    #[display(
+        "Conflict({name}: {}; available: {})",
+        display_list(requirements),
+        display_list(available)
+    )]
+    Conflict {
+        name: StringName,
+        requirements: Vec<Requirement>,
+        available: Vec<semver::Version>,
+    },
+}
+
+impl ResolveError {
+    #[track_caller]
+    pub fn throw(self) -> GenericError<Self> {
+        GenericError::throw(self)
+    }
+}
+
+fn display_list<T: std::fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Where two builds of an assembly that should be identical first differ.
+#[derive(Clone, Debug, Display, thiserror::Error)]
+pub enum ReproducibilityError {
+    /// `section` is `None` within the header and directory.
+    #[display("BytesDiffer(at offset {offset} in {section:?})")]
+    BytesDiffer {
+        offset: u64,
+        section: Option<SectionKind>,
+    },
+}
+
+impl ReproducibilityError {
+    #[track_caller]
+    pub fn throw(self) -> GenericError<Self> {
+        GenericError::throw(self)
+    }
+}
+
+/// Why a new version of an assembly cannot replace the old one, see
+/// [`crate::diff::check_compatible`].
+#[derive(Clone, Debug, Display, thiserror::Error)]
+pub enum CompatibilityError {
+    #[display("BreakingChanges({version}: {})", display_list(changes))]
+    BreakingChanges {
+        version: semver::Version,
+        changes: Vec<Change>,
+    },
+    /// The new version is lower than the old one.
+    #[display("Downgrade({old} -> {new})")]
+    Downgrade {
+        old: semver::Version,
+        new: semver::Version,
+    },
+}
+
+impl CompatibilityError {
+    #[track_caller]
+    pub fn throw(self) -> GenericError<Self> {
+        GenericError::throw(self)
+    }
+}
+
+#[derive(Clone, Debug, Display, thiserror::Error)]
+pub enum LinkError {
+    #[display("DuplicateModule({_0})")]
+    DuplicateModule(StringName),
+    #[display("DuplicateTypes({})", display_list(_0))]
+    DuplicateTypes(Vec<DuplicateType>),
+}
+
+impl LinkError {
+    #[track_caller]
+    pub fn throw(self) -> GenericError<Self> {
+        GenericError::throw(self)
+    }
+}
@@ -0,0 +1,46 @@
+//! Definitions shared by the tests of several modules. Tests start from these and only
+//! build what they need on top.
+
+use crate::attrs::{
+    ClassImplementationFlags, MethodAttr, MethodImplementationFlags, TypeAttr, TypeSpecificAttr,
+    Visibility,
+};
+use crate::core_types;
+use crate::instruction::StringInstruction;
+use crate::metadata::{AssemblyDef, MethodDef, MethodSignature, TypeDef};
+
+/// A public static method with one register, no parameters and a `void` return type.
+pub(crate) fn static_method(name: &str, body: Vec<StringInstruction>) -> MethodDef {
+    MethodDef::builder()
+        .name(name)
+        .attr(MethodAttr::new(
+            Visibility::Public,
+            MethodImplementationFlags::Static.into(),
+            1,
+        ))
+        .signature(MethodSignature::builder().build())
+        .body(body)
+        .build()
+}
+
+/// A public static class deriving from `System.Object`.
+pub(crate) fn static_class(name: &str, methods: Vec<MethodDef>) -> TypeDef {
+    TypeDef::builder()
+        .name(name)
+        .attr(TypeAttr::new(
+            Visibility::Public,
+            TypeSpecificAttr::Class(ClassImplementationFlags::Static.into()),
+        ))
+        .parent(core_types::OBJECT)
+        .methods(methods)
+        .build()
+}
+
+/// The assembly `App` with a class `App.Program` whose `Main()` runs `body`.
+pub(crate) fn program(body: Vec<StringInstruction>) -> AssemblyDef {
+    let main = static_method("Main()", body);
+    AssemblyDef::builder()
+        .name("App")
+        .types(vec![static_class("App.Program", vec![main])])
+        .build()
+}
@@ -0,0 +1,287 @@
+//! Declarations of generic parameters and checking of `type_vars` maps against them.
+
+use crate::attrs::{TypeHierarchy, borsh_flags, check_flags};
+use crate::errors::{AttrError, GenericError, MakeGenericError};
+use crate::{StringName, StringTypeReference};
+use borsh::{BorshDeserialize, BorshSerialize};
+use derive_ctor::ctor;
+use enumflags2::{BitFlags, bitflags};
+use getset::{CopyGetters, Getters, MutGetters, Setters};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+#[repr(u8)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[borsh(use_discriminant = true)]
+pub enum Variance {
+    #[default]
+    Invariant = 0,
+    /// Contravariant, only used as input.
+    In = 1,
+    /// Covariant, only used as output.
+    Out = 2,
+}
+
+#[bitflags]
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum GenericConstraintFlags {
+    /// Must be a value type.
+    Struct,
+    /// Must be a reference type.
+    Class,
+    /// Must have a public parameterless constructor.
+    DefaultCtor,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    ctor,
+    Getters,
+    CopyGetters,
+    MutGetters,
+    Setters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[ctor(pub new)]
+#[getset(get = "pub", set = "pub", get_mut = "pub")]
+pub struct GenericParamDef {
+    name: StringName,
+    #[getset(skip)]
+    #[get_copy = "pub"]
+    #[set = "pub"]
+    variance: Variance,
+    #[ctor(default)]
+    base_class: Option<StringTypeReference>,
+    #[ctor(default)]
+    interfaces: Vec<StringTypeReference>,
+    #[ctor(default)]
+    #[getset(skip)]
+    #[get_copy = "pub"]
+    #[set = "pub"]
+    #[borsh(
+        serialize_with = "borsh_flags::serialize",
+        deserialize_with = "borsh_flags::deserialize"
+    )]
+    flags: BitFlags<GenericConstraintFlags>,
+}
+
+impl GenericParamDef {
+    const FLAG_CONFLICTS: &[(GenericConstraintFlags, GenericConstraintFlags)] = &[(
+        GenericConstraintFlags::Struct,
+        GenericConstraintFlags::Class,
+    )];
+
+    #[track_caller]
+    pub fn validate(&self) -> Result<(), GenericError<AttrError>> {
+        check_flags(self.flags, Self::FLAG_CONFLICTS, &[])?;
+        if self.base_class.is_some() && self.flags.contains(GenericConstraintFlags::Struct) {
+            return Err(AttrError::ConflictingFlags(
+                format!("{:?}", GenericConstraintFlags::Struct),
+                "base class".to_owned(),
+            )
+            .throw());
+        }
+        Ok(())
+    }
+
+    /// Checks one concrete argument. Constraints may mention other parameters, which are
+    /// substituted from `type_vars` first.
+    #[track_caller]
+    pub fn check_arg<C: GenericContext + ?Sized>(
+        &self,
+        arg: &StringTypeReference,
+        type_vars: &IndexMap<StringName, StringTypeReference>,
+        ctx: &C,
+    ) -> Result<(), GenericError<MakeGenericError>> {
+        let param = || self.name.clone();
+        let got = || Box::new(arg.clone());
+        if self.flags.contains(GenericConstraintFlags::Struct) && !ctx.is_value_type(arg) {
+            return Err(MakeGenericError::NotValueType {
+                param: param(),
+                got: got(),
+            }
+            .throw());
+        }
+        if self.flags.contains(GenericConstraintFlags::Class) && ctx.is_value_type(arg) {
+            return Err(MakeGenericError::NotReferenceType {
+                param: param(),
+                got: got(),
+            }
+            .throw());
+        }
+        if self.flags.contains(GenericConstraintFlags::DefaultCtor) && !ctx.has_default_ctor(arg) {
+            return Err(MakeGenericError::NoDefaultCtor {
+                param: param(),
+                got: got(),
+            }
+            .throw());
+        }
+        if let Some(base) = &self.base_class {
+            let expected = base.substitute(type_vars);
+            if *arg != expected && !ctx.is_subclass_of(arg, &expected) {
+                return Err(MakeGenericError::NotSubclass {
+                    param: param(),
+                    got: got(),
+                    expected: Box::new(expected),
+                }
+                .throw());
+            }
+        }
+        for interface in self.interfaces.iter() {
+            let interface = interface.substitute(type_vars);
+            if !ctx.implements(arg, &interface) {
+                return Err(MakeGenericError::NotImplemented {
+                    param: param(),
+                    got: got(),
+                    interface: Box::new(interface),
+                }
+                .throw());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Type information needed to check generic constraints.
+pub trait GenericContext: TypeHierarchy {
+    fn is_value_type(&self, ty: &StringTypeReference) -> bool;
+    fn has_default_ctor(&self, ty: &StringTypeReference) -> bool;
+    /// Whether `ty` implements `interface`, directly or through a base class.
+    fn implements(&self, ty: &StringTypeReference, interface: &StringTypeReference) -> bool;
+}
+
+/// Checks that `type_vars` binds exactly the declared parameters and that every binding
+/// satisfies its constraints.
+#[track_caller]
+pub fn check_type_vars<C: GenericContext + ?Sized>(
+    params: &[GenericParamDef],
+    type_vars: &IndexMap<StringName, StringTypeReference>,
+    ctx: &C,
+) -> Result<(), GenericError<MakeGenericError>> {
+    if let Some(unknown) = type_vars
+        .keys()
+        .find(|k| !params.iter().any(|x| x.name == **k))
+    {
+        return Err(MakeGenericError::UnknownTypeVar(unknown.clone()).throw());
+    }
+    for param in params {
+        let arg = type_vars
+            .get(&param.name)
+            .ok_or(MakeGenericError::MissingTypeVar(param.name.clone()).throw())?;
+        param.check_arg(arg, type_vars, ctx)?;
+    }
+    Ok(())
+}
+
+/// Types and methods that declare generic parameters.
+pub trait HasGenericParams {
+    fn generic_params(&self) -> &[GenericParamDef];
+    fn generic_params_mut(&mut self) -> &mut Vec<GenericParamDef>;
+
+    fn is_generic_definition(&self) -> bool {
+        !self.generic_params().is_empty()
+    }
+    fn generic_param(&self, name: &str) -> Option<&GenericParamDef> {
+        self.generic_params().iter().find(|x| x.name == *name)
+    }
+    #[track_caller]
+    fn check_type_vars<C: GenericContext + ?Sized>(
+        &self,
+        type_vars: &IndexMap<StringName, StringTypeReference>,
+        ctx: &C,
+    ) -> Result<(), GenericError<MakeGenericError>> {
+        check_type_vars(self.generic_params(), type_vars, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Context;
+
+    impl TypeHierarchy for Context {
+        fn is_subclass_of(
+            &self,
+            derived: &StringTypeReference,
+            base: &StringTypeReference,
+        ) -> bool {
+            derived.string_name_repr().as_str() == "[App]Derived"
+                && base.string_name_repr().as_str() == "[Lib]Base"
+        }
+    }
+
+    impl GenericContext for Context {
+        fn is_value_type(&self, ty: &StringTypeReference) -> bool {
+            crate::core_types::PrimitiveType::from_type_ref(ty).is_some()
+        }
+        fn has_default_ctor(&self, ty: &StringTypeReference) -> bool {
+            self.is_value_type(ty)
+        }
+        fn implements(&self, ty: &StringTypeReference, interface: &StringTypeReference) -> bool {
+            *ty == crate::core_types::INT32
+                && interface.string_name_repr().as_str() == "[Lib]IComparable[@T:[!]System.Int32]"
+        }
+    }
+
+    fn ty(s: &str) -> StringTypeReference {
+        StringTypeReference::from_string_repr(s).unwrap()
+    }
+
+    #[test]
+    fn test_check_type_vars() {
+        let mut comparable = GenericParamDef::new(StringName::from("@T"), Variance::In);
+        comparable
+            .set_flags(GenericConstraintFlags::Struct | GenericConstraintFlags::DefaultCtor)
+            .interfaces_mut()
+            .push(ty("[Lib]IComparable[@T:@T]"));
+        let mut derived = GenericParamDef::new(StringName::from("@U"), Variance::Out);
+        derived.set_base_class(Some(ty("[Lib]Base")));
+        let params = [comparable, derived];
+        params.iter().try_for_each(|x| x.validate()).unwrap();
+
+        let bind = |t: &str, u: &str| -> IndexMap<StringName, StringTypeReference> {
+            [
+                (StringName::from("@T"), ty(t)),
+                (StringName::from("@U"), ty(u)),
+            ]
+            .into()
+        };
+        check_type_vars(&params, &bind("[!]System.Int32", "[App]Derived"), &Context).unwrap();
+        assert!(check_type_vars(&params, &bind("[!]System.Int32", "[Lib]Base"), &Context).is_ok());
+        for (t, u) in [
+            ("[!]System.String", "[Lib]Base"),
+            ("[!]System.Int64", "[Lib]Base"),
+            ("[!]System.Int32", "[App]Other"),
+        ] {
+            assert!(
+                check_type_vars(&params, &bind(t, u), &Context).is_err(),
+                "{t} {u}"
+            );
+        }
+        let missing = IndexMap::from([(StringName::from("@T"), ty("[!]System.Int32"))]);
+        assert!(check_type_vars(&params, &missing, &Context).is_err());
+
+        let mut invalid = GenericParamDef::new(StringName::from("@V"), Variance::Invariant);
+        invalid.set_flags(GenericConstraintFlags::Struct | GenericConstraintFlags::Class);
+        assert!(invalid.validate().is_err());
+    }
+}
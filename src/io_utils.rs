@@ -1 +1,340 @@
+//! Position-tracking binary readers and writers shared by the toolchain's file formats.
+//!
+//! Fixed-width integers are little-endian; variable-width ones are LEB128. Strings and
+//! byte strings are prefixed by their length as unsigned LEB128.
 
+use crate::errors::{BinaryError, GenericError};
+use std::io::{BorrowedBuf, ErrorKind, IoSlice, Read, Write};
+
+/// Reads are capped at this size per allocation step, so a corrupt length prefix fails
+/// with `BinaryTooShort` instead of reserving gigabytes up front.
+const READ_CHUNK: usize = 64 * 1024;
+
+macro impl_fixed_int($($t:ident => $write:ident, $read:ident);* $(;)?) {
+    impl<W: Write> BinaryWriter<W> {
+        $(
+            pub fn $write(&mut self, x: $t) -> std::io::Result<()> {
+                self.write_bytes(&x.to_le_bytes())
+            }
+        )*
+    }
+
+    impl<R: Read> BinaryReader<R> {
+        $(
+            #[track_caller]
+            pub fn $read(&mut self) -> Result<$t, GenericError<BinaryError>> {
+                self.read_array().map($t::from_le_bytes)
+            }
+        )*
+    }
+}
+
+impl_fixed_int! {
+    u8 => write_u8, read_u8;
+    i8 => write_i8, read_i8;
+    u16 => write_u16, read_u16;
+    i16 => write_i16, read_i16;
+    u32 => write_u32, read_u32;
+    i32 => write_i32, read_i32;
+    u64 => write_u64, read_u64;
+    i64 => write_i64, read_i64;
+}
+
+pub struct BinaryWriter<W> {
+    inner: W,
+    position: u64,
+}
+
+impl<W: Write> BinaryWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, position: 0 }
+    }
+    /// Bytes written so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.inner.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+    /// Writes several buffers, e.g. the sections of a file, in as few calls as the
+    /// underlying writer allows.
+    pub fn write_vectored(&mut self, parts: &[&[u8]]) -> std::io::Result<()> {
+        if self.inner.is_write_vectored() {
+            let mut slices = parts.iter().map(|x| IoSlice::new(x)).collect::<Vec<_>>();
+            self.inner.write_all_vectored(&mut slices)?;
+        } else {
+            parts.iter().try_for_each(|x| self.inner.write_all(x))?;
+        }
+        self.position += parts.iter().map(|x| x.len() as u64).sum::<u64>();
+        Ok(())
+    }
+    pub fn write_uleb128(&mut self, mut x: u64) -> std::io::Result<()> {
+        let mut buf = [0u8; 10];
+        let mut len = 0;
+        loop {
+            let byte = (x & 0x7f) as u8;
+            x >>= 7;
+            if x == 0 {
+                buf[len] = byte;
+                len += 1;
+                break;
+            }
+            buf[len] = byte | 0x80;
+            len += 1;
+        }
+        self.write_bytes(&buf[..len])
+    }
+    pub fn write_sleb128(&mut self, mut x: i64) -> std::io::Result<()> {
+        let mut buf = [0u8; 10];
+        let mut len = 0;
+        loop {
+            let byte = (x & 0x7f) as u8;
+            x >>= 7;
+            let done = (x == 0 && byte & 0x40 == 0) || (x == -1 && byte & 0x40 != 0);
+            buf[len] = if done { byte } else { byte | 0x80 };
+            len += 1;
+            if done {
+                break;
+            }
+        }
+        self.write_bytes(&buf[..len])
+    }
+    pub fn write_prefixed_bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.write_uleb128(bytes.len() as u64)?;
+        self.write_bytes(bytes)
+    }
+    pub fn write_str(&mut self, s: &str) -> std::io::Result<()> {
+        self.write_prefixed_bytes(s.as_bytes())
+    }
+    /// Pads with zeros up to the next multiple of `alignment`, which must not be 0.
+    pub fn align(&mut self, alignment: u64) -> std::io::Result<()> {
+        assert_ne!(alignment, 0, "zero alignment");
+        let padding = self.position.next_multiple_of(alignment) - self.position;
+        self.write_bytes(&vec![0; padding as usize])
+    }
+}
+
+impl BinaryWriter<Vec<u8>> {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.inner
+    }
+}
+
+pub struct BinaryReader<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> BinaryReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, position: 0 }
+    }
+    /// Bytes consumed so far; errors from this reader carry it as their offset.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// `e` located at the current position.
+    #[track_caller]
+    pub fn error(&self, e: BinaryError) -> GenericError<BinaryError> {
+        e.at(self.position).throw()
+    }
+    #[track_caller]
+    fn io_error(&self, e: std::io::Error) -> GenericError<BinaryError> {
+        self.error(match e.kind() {
+            ErrorKind::UnexpectedEof => BinaryError::BinaryTooShort,
+            kind => BinaryError::Io(kind),
+        })
+    }
+
+    #[track_caller]
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], GenericError<BinaryError>> {
+        let mut buf = [0u8; N];
+        self.inner
+            .read_exact(&mut buf)
+            .map_err(|e| self.io_error(e))?;
+        self.position += N as u64;
+        Ok(buf)
+    }
+    #[track_caller]
+    pub fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, GenericError<BinaryError>> {
+        let mut out = Vec::new();
+        while out.len() < len {
+            let chunk = (len - out.len()).min(READ_CHUNK);
+            out.reserve_exact(chunk);
+            let mut buf = BorrowedBuf::from(&mut out.spare_capacity_mut()[..chunk]);
+            self.inner
+                .read_buf_exact(buf.unfilled())
+                .map_err(|e| self.io_error(e))?;
+            // SAFETY: `read_buf_exact` initialized all `chunk` bytes.
+            unsafe { out.set_len(out.len() + chunk) };
+            self.position += chunk as u64;
+        }
+        Ok(out)
+    }
+    #[track_caller]
+    pub fn read_uleb128(&mut self) -> Result<u64, GenericError<BinaryError>> {
+        let start = self.position;
+        let mut x = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            let bits = (byte & 0x7f) as u64;
+            if shift == 63 && bits > 1 {
+                break;
+            }
+            x |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(x);
+            }
+        }
+        Err(BinaryError::WrongFileFormat.at(start).throw())
+    }
+    #[track_caller]
+    pub fn read_sleb128(&mut self) -> Result<i64, GenericError<BinaryError>> {
+        let start = self.position;
+        let mut x = 0i64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            // the last byte holds bit 63 only, the rest must repeat it as the sign
+            if shift == 63 && !matches!(byte & 0x7f, 0 | 0x7f) {
+                break;
+            }
+            x |= ((byte & 0x7f) as i64) << shift;
+            if byte & 0x80 == 0 {
+                if shift < 57 && byte & 0x40 != 0 {
+                    x |= -1 << (shift + 7);
+                }
+                return Ok(x);
+            }
+        }
+        Err(BinaryError::WrongFileFormat.at(start).throw())
+    }
+    #[track_caller]
+    pub fn read_prefixed_bytes(&mut self) -> Result<Vec<u8>, GenericError<BinaryError>> {
+        let len = self.read_uleb128()?;
+        self.read_bytes(len as usize)
+    }
+    #[track_caller]
+    pub fn read_string(&mut self) -> Result<String, GenericError<BinaryError>> {
+        let start = self.position;
+        String::from_utf8(self.read_prefixed_bytes()?)
+            .map_err(|_| BinaryError::WrongFileFormat.at(start).throw())
+    }
+    /// Skips the zero padding written by [`BinaryWriter::align`]; `alignment` must not be 0.
+    #[track_caller]
+    pub fn align(&mut self, alignment: u64) -> Result<(), GenericError<BinaryError>> {
+        assert_ne!(alignment, 0, "zero alignment");
+        let padding = self.position.next_multiple_of(alignment) - self.position;
+        for _ in 0..padding {
+            if self.read_u8()? != 0 {
+                return Err(self.error(BinaryError::WrongFileFormat));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// In-memory reading can hand out borrowed slices instead of copies.
+impl<'a> BinaryReader<&'a [u8]> {
+    pub fn remaining(&self) -> &'a [u8] {
+        self.inner
+    }
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+    #[track_caller]
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], GenericError<BinaryError>> {
+        let (taken, rest) = self
+            .inner
+            .split_at_checked(len)
+            .ok_or_else(|| self.error(BinaryError::BinaryTooShort))?;
+        self.inner = rest;
+        self.position += len as u64;
+        Ok(taken)
+    }
+    #[track_caller]
+    pub fn take_str(&mut self) -> Result<&'a str, GenericError<BinaryError>> {
+        let start = self.position;
+        let len = self.read_uleb128()?;
+        str::from_utf8(self.take(len as usize)?)
+            .map_err(|_| BinaryError::WrongFileFormat.at(start).throw())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let mut writer = BinaryWriter::new(Vec::new());
+        let unsigned = [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+        let signed = [0, 1, -1, 63, -64, 64, -65, i64::MIN, i64::MAX];
+        unsigned
+            .iter()
+            .try_for_each(|x| writer.write_uleb128(*x))
+            .unwrap();
+        signed
+            .iter()
+            .try_for_each(|x| writer.write_sleb128(*x))
+            .unwrap();
+        writer.write_u16(0xBEEF).unwrap();
+        writer.write_str("héllo").unwrap();
+        writer.align(8).unwrap();
+        assert_eq!(writer.position() % 8, 0);
+        writer.write_vectored(&[b"ab", b"", b"cde"]).unwrap();
+        let bytes = writer.into_bytes();
+
+        let mut reader = BinaryReader::new(&bytes[..]);
+        for x in unsigned {
+            assert_eq!(reader.read_uleb128().unwrap(), x);
+        }
+        for x in signed {
+            assert_eq!(reader.read_sleb128().unwrap(), x);
+        }
+        assert_eq!(reader.read_u16().unwrap(), 0xBEEF);
+        assert_eq!(reader.take_str().unwrap(), "héllo");
+        reader.align(8).unwrap();
+        assert_eq!(reader.take(5).unwrap(), b"abcde");
+        assert!(reader.is_empty());
+
+        let mut streaming = BinaryReader::new(std::io::Cursor::new(&bytes));
+        assert_eq!(streaming.read_uleb128().unwrap(), 0);
+        assert_eq!(streaming.read_bytes(1).unwrap(), [1]);
+        assert_eq!(streaming.position(), 2);
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        let mut reader = BinaryReader::new(&[0x80, 0x80][..]);
+        let e = reader.read_uleb128().unwrap_err().into_error();
+        assert!(matches!(e.root(), BinaryError::BinaryTooShort));
+        assert!(matches!(e, BinaryError::AtOffset { offset: 2, .. }));
+
+        let mut reader = BinaryReader::new(&[0xff; 11][..]);
+        let e = reader.read_uleb128().unwrap_err().into_error();
+        assert!(matches!(e.root(), BinaryError::WrongFileFormat));
+
+        for last in [0x01, 0x40, 0x80] {
+            let mut bytes = [0x80; 10];
+            bytes[9] = last;
+            let mut reader = BinaryReader::new(&bytes[..]);
+            assert!(reader.read_sleb128().is_err(), "{last:#x}");
+        }
+
+        let mut reader = BinaryReader::new(&[0x05, b'a'][..]);
+        assert!(reader.read_prefixed_bytes().is_err());
+    }
+}
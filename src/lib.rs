@@ -22,23 +22,40 @@ pub extern crate iota;
 #[doc(hidden)]
 pub extern crate paste;
 
+pub mod assembly_view;
 pub mod attrs;
 pub mod configs;
+pub mod container;
+pub mod core_types;
+pub mod diff;
 pub mod errors;
 pub mod find_util;
+pub mod generics;
 pub mod instruction;
 pub mod io_utils;
+pub mod linker;
 pub mod macros;
+pub mod mangling;
+pub mod manifest;
+pub mod metadata;
+pub mod migration;
+pub mod reproducible;
 pub mod traits;
 
 pub mod color;
+#[cfg(test)]
+mod fixtures;
 pub mod path_searcher;
+mod qualified_name;
 mod string_name;
 mod string_reference;
+mod symbol;
 
 pub use macros::*;
+pub use qualified_name::QualifiedName;
 pub use string_name::StringName;
 pub use string_reference::{StringMethodReference, StringTypeReference};
+pub use symbol::{Symbol, SymbolTable};
 
 // Re-exports
 pub use anyhow::{Error, Result};
@@ -0,0 +1,332 @@
+//! Merges separately compiled modules into one assembly.
+//!
+//! Every reference to a merged module, in types, signatures, attributes, generic
+//! constraints and method bodies, is rewritten to the linked assembly. The string table
+//! is shared once the result is written, see [`crate::container`].
+
+use crate::attrs::{ConstValue, CustomAttribute, HasCustomAttributes};
+use crate::errors::{GenericError, LinkError};
+use crate::generics::{GenericParamDef, HasGenericParams};
+use crate::instruction::StringInstruction;
+use crate::manifest::AssemblyReference;
+use crate::metadata::{AssemblyDef, FieldDef, MethodDef, TypeDef};
+use crate::string_reference::rename_assemblies_in_method_name;
+use crate::{StringMethodReference, StringName, StringTypeReference};
+use derive_ctor::ctor;
+use getset::Getters;
+use indexmap::IndexMap;
+use semver::{Version, VersionReq};
+use std::fmt::{Display, Formatter};
+
+/// A type defined differently by two modules.
+#[derive(Clone, Debug, PartialEq, Eq, ctor, Getters)]
+#[getset(get = "pub")]
+pub struct DuplicateType {
+    ty: StringName,
+    first: StringName,
+    second: StringName,
+}
+
+impl Display for DuplicateType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in {} and {}", self.ty, self.first, self.second)
+    }
+}
+
+pub struct Linker {
+    name: StringName,
+    version: Version,
+    modules: Vec<AssemblyDef>,
+}
+
+impl Linker {
+    pub fn new(name: impl Into<StringName>, version: Version) -> Self {
+        Self {
+            name: name.into(),
+            version,
+            modules: Vec::new(),
+        }
+    }
+    pub fn add_module(&mut self, module: AssemblyDef) -> &mut Self {
+        self.modules.push(module);
+        self
+    }
+
+    /// Merges the modules in the order they were added.
+    ///
+    /// A type defined by several modules is kept once if the definitions are equal after
+    /// renaming, and reported otherwise. References to assemblies outside the modules are
+    /// merged, with the requirements on the same assembly combined.
+    #[track_caller]
+    pub fn link(&self) -> Result<AssemblyDef, GenericError<LinkError>> {
+        let mut renames = IndexMap::new();
+        for module in &self.modules {
+            if renames
+                .insert(module.name().clone(), self.name.clone())
+                .is_some()
+            {
+                return Err(LinkError::DuplicateModule(module.name().clone()).throw());
+            }
+        }
+        let renamer = Renamer(&renames);
+
+        let mut types = IndexMap::<StringName, (&StringName, TypeDef)>::new();
+        let mut duplicates = Vec::new();
+        for module in &self.modules {
+            for ty in module.types() {
+                let mut ty = ty.clone();
+                renamer.type_def(&mut ty);
+                match types.get(ty.name()) {
+                    Some((_, existing)) if *existing == ty => {}
+                    Some((first, _)) => duplicates.push(DuplicateType::new(
+                        ty.name().clone(),
+                        (*first).clone(),
+                        module.name().clone(),
+                    )),
+                    None => {
+                        types.insert(ty.name().clone(), (module.name(), ty));
+                    }
+                }
+            }
+        }
+        if !duplicates.is_empty() {
+            return Err(LinkError::DuplicateTypes(duplicates).throw());
+        }
+
+        let mut references = IndexMap::<StringName, VersionReq>::new();
+        for reference in self.modules.iter().flat_map(|x| x.references()) {
+            if renames.contains_key(reference.name()) {
+                continue;
+            }
+            let req = references
+                .entry(reference.name().clone())
+                .or_insert(VersionReq::STAR);
+            for comparator in &reference.req().comparators {
+                if !req.comparators.contains(comparator) {
+                    req.comparators.push(comparator.clone());
+                }
+            }
+        }
+
+        Ok(AssemblyDef::builder()
+            .name(self.name.clone())
+            .version(self.version.clone())
+            .references(
+                references
+                    .into_iter()
+                    .map(|(name, req)| AssemblyReference::new(name, req))
+                    .collect(),
+            )
+            .types(types.into_values().map(|(_, ty)| ty).collect())
+            .build())
+    }
+}
+
+/// Rewrites the assembly names of every reference in a definition.
+struct Renamer<'r>(&'r IndexMap<StringName, StringName>);
+
+impl Renamer<'_> {
+    fn ty(&self, ty: &mut StringTypeReference) {
+        *ty = ty.rename_assemblies(self.0);
+    }
+    fn method_name(&self, name: &mut StringName) {
+        *name = rename_assemblies_in_method_name(name, self.0);
+    }
+    fn method(&self, method: &mut StringMethodReference) {
+        *method = method.rename_assemblies(self.0);
+    }
+
+    fn type_def(&self, ty: &mut TypeDef) {
+        ty.parent_mut().iter_mut().for_each(|x| self.ty(x));
+        ty.interfaces_mut().iter_mut().for_each(|x| self.ty(x));
+        self.custom_attrs(ty.custom_attrs_mut());
+        self.generic_params(ty.generic_params_mut());
+        ty.fields_mut().iter_mut().for_each(|x| self.field(x));
+        ty.methods_mut().iter_mut().for_each(|x| self.method_def(x));
+    }
+    fn field(&self, field: &mut FieldDef) {
+        self.ty(field.ty_mut());
+        self.custom_attrs(field.custom_attrs_mut());
+        if let Some(value) = field.const_value_mut() {
+            self.const_value(value);
+        }
+    }
+    fn method_def(&self, method: &mut MethodDef) {
+        self.method_name(method.name_mut());
+        let signature = method.signature_mut();
+        signature
+            .params_mut()
+            .iter_mut()
+            .for_each(|x| self.ty(x.ty_mut()));
+        self.ty(signature.ret_type_mut());
+        self.custom_attrs(method.custom_attrs_mut());
+        self.generic_params(method.generic_params_mut());
+        method
+            .body_mut()
+            .iter_mut()
+            .for_each(|x| self.instruction(x));
+    }
+    fn custom_attrs(&self, attrs: &mut [CustomAttribute]) {
+        for attr in attrs {
+            self.ty(attr.ty_mut());
+            attr.ctor_args_mut()
+                .iter_mut()
+                .for_each(|x| self.const_value(x));
+            attr.named_args_mut()
+                .values_mut()
+                .for_each(|x| self.const_value(x));
+        }
+    }
+    fn const_value(&self, value: &mut ConstValue) {
+        match value {
+            ConstValue::Type(ty) => self.ty(ty),
+            ConstValue::Array(elem, values) => {
+                self.ty(elem);
+                values.iter_mut().for_each(|x| self.const_value(x));
+            }
+            _ => {}
+        }
+    }
+    fn generic_params(&self, params: &mut [GenericParamDef]) {
+        for param in params {
+            param.base_class_mut().iter_mut().for_each(|x| self.ty(x));
+            param.interfaces_mut().iter_mut().for_each(|x| self.ty(x));
+        }
+    }
+    #[allow(deprecated)]
+    fn instruction(&self, instruction: &mut StringInstruction) {
+        match instruction {
+            StringInstruction::NewObject { ty, ctor_name, .. } => {
+                self.ty(ty);
+                self.method_name(ctor_name);
+            }
+            StringInstruction::InstanceCall { method, .. } => self.method(method),
+            StringInstruction::StaticCall { ty, method, .. } => {
+                self.ty(ty);
+                self.method(method);
+            }
+            StringInstruction::LoadStatic { ty, .. } => self.ty(ty),
+            StringInstruction::LoadTrue { .. }
+            | StringInstruction::LoadFalse { .. }
+            | StringInstruction::Load_u8 { .. }
+            | StringInstruction::Load_u8_0 { .. }
+            | StringInstruction::Load_u8_1 { .. }
+            | StringInstruction::Load_u8_2 { .. }
+            | StringInstruction::Load_u8_3 { .. }
+            | StringInstruction::Load_u8_4 { .. }
+            | StringInstruction::Load_u8_5 { .. }
+            | StringInstruction::Load_u64 { .. }
+            | StringInstruction::LoadArg { .. }
+            | StringInstruction::LoadAllArgsAsArray { .. }
+            | StringInstruction::SetField { .. }
+            | StringInstruction::ReturnVal { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::{encode_assembly, read_assembly};
+    use crate::fixtures;
+    use crate::metadata::ParamDef;
+
+    fn ty(s: &str) -> StringTypeReference {
+        StringTypeReference::from_string_repr(s).unwrap()
+    }
+
+    fn method(name: &str, params: Vec<ParamDef>, body: Vec<StringInstruction>) -> MethodDef {
+        let mut method = fixtures::static_method(name, body);
+        *method.signature_mut().params_mut() = params;
+        method
+    }
+
+    #[test]
+    fn test_link() {
+        let ret = StringInstruction::ReturnVal { register_addr: 0 };
+        let collections = AssemblyDef::builder()
+            .name("Std.Collections")
+            .version(Version::new(1, 0, 0))
+            .references(vec![AssemblyReference::new(
+                StringName::from("Native"),
+                "^1.2".parse().unwrap(),
+            )])
+            .types(vec![fixtures::static_class(
+                "System.List",
+                vec![method("Clear()", vec![], vec![ret.clone()])],
+            )])
+            .build();
+        let call = StringInstruction::StaticCall {
+            ty: ty("[Std.Collections]System.List"),
+            method: StringMethodReference::static_single("Sort([Std.Collections]System.List)"),
+            args: vec![],
+            ret_at: 0,
+        };
+        let param = ParamDef::builder()
+            .name("list")
+            .ty(ty("[Std.Collections]System.List[]"))
+            .build();
+        let linq = AssemblyDef::builder()
+            .name("Std.Linq")
+            .references(vec![
+                AssemblyReference::new(StringName::from("Std.Collections"), "1".parse().unwrap()),
+                AssemblyReference::new(StringName::from("Native"), "<1.5".parse().unwrap()),
+            ])
+            .types(vec![fixtures::static_class(
+                "System.Linq",
+                vec![method(
+                    "First([Std.Collections]System.List[])",
+                    vec![param],
+                    vec![call, ret.clone()],
+                )],
+            )])
+            .build();
+
+        let mut linker = Linker::new("Std", Version::new(2, 0, 0));
+        linker.add_module(collections.clone()).add_module(linq);
+        let std = linker.link().unwrap();
+        assert_eq!(std.types().len(), 2);
+        let first = std.find_type("System.Linq").unwrap().methods()[0].clone();
+        assert_eq!(first.name().as_str(), "First([Std]System.List[])");
+        assert_eq!(
+            first.signature().params()[0].ty(),
+            &ty("[Std]System.List[]")
+        );
+        assert!(matches!(
+            &first.body()[0],
+            StringInstruction::StaticCall { ty: t, method, .. }
+                if *t == ty("[Std]System.List")
+                    && method.string_name_repr().as_str() == "Sort([Std]System.List)"
+        ));
+        let renames = [(StringName::from("Std.Linq"), StringName::from("Std"))].into();
+        let zip =
+            StringName::from("Zip(([Std.Linq]A,fn([Std.Linq]B[])->[!]C),[Lib]D[K:[Std.Linq]K])");
+        assert_eq!(
+            rename_assemblies_in_method_name(&zip, &renames).as_str(),
+            "Zip(([Std]A,fn([Std]B[])->[!]C),[Lib]D[K:[Std]K])"
+        );
+        assert_eq!(std.references().len(), 1);
+        assert_eq!(std.references()[0].req().to_string(), "^1.2, <1.5");
+        assert_eq!(read_assembly(&encode_assembly(&std)).unwrap(), std);
+
+        let mut other = collections.clone();
+        *other.name_mut() = StringName::from("Std.Other");
+        other.types_mut()[0].methods_mut().clear();
+        let mut linker = Linker::new("Std", Version::new(2, 0, 0));
+        linker.add_module(collections.clone()).add_module(other);
+        let e = linker.link().unwrap_err().into_error();
+        assert_eq!(
+            e.to_string(),
+            "DuplicateTypes(System.List in Std.Collections and Std.Other)"
+        );
+
+        let mut linker = Linker::new("Std", Version::new(2, 0, 0));
+        linker
+            .add_module(collections.clone())
+            .add_module(collections);
+        assert!(matches!(
+            linker.link().unwrap_err().into_error(),
+            LinkError::DuplicateModule(_)
+        ));
+    }
+}
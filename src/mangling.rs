@@ -0,0 +1,380 @@
+//! Reversible mangling of type and method references into `[A-Za-z0-9_]` symbols.
+//!
+//! ```text
+//! type-symbol   := "_PLT" type
+//! method-symbol := "_PLM" type method
+//! type   := "S" ident ident                    single (assembly, type)
+//!         | "G" ident                          generic variable
+//!         | "W" ident ident count type-vars    generic instance
+//!         | "A" type | "N" type | "P" type     array, nullable, pointer
+//!         | "T" count type*                    tuple
+//!         | "F" count type* type               function (params, return)
+//! method := "S" ident | "W" ident count type-vars
+//! type-vars := (ident type)*
+//! count  := decimal "_"
+//! ident  := decimal "_" escaped
+//! ```
+//!
+//! `escaped` keeps ASCII letters and digits, writes `_` as `__` and any other char as
+//! `_<lowercase hex code point>_`; the leading decimal is the escaped length in bytes.
+
+use crate::errors::{GenericError, ParseStrError};
+use crate::string_reference::MAX_TYPE_DEPTH;
+use crate::{StringMethodReference, StringName, StringTypeReference};
+use indexmap::IndexMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+pub const TYPE_SYMBOL_PREFIX: &str = "_PLT";
+pub const METHOD_SYMBOL_PREFIX: &str = "_PLM";
+
+pub fn mangle_type(ty: &StringTypeReference) -> String {
+    let mut out = String::from(TYPE_SYMBOL_PREFIX);
+    write_type(&mut out, ty);
+    out
+}
+
+pub fn mangle_method(owner: &StringTypeReference, method: &StringMethodReference) -> String {
+    let mut out = String::from(METHOD_SYMBOL_PREFIX);
+    write_type(&mut out, owner);
+    match method {
+        StringMethodReference::Single(name) => {
+            out.push('S');
+            write_ident(&mut out, name);
+        }
+        StringMethodReference::WithGeneric(name, type_vars) => {
+            out.push('W');
+            write_ident(&mut out, name);
+            write_type_vars(&mut out, type_vars);
+        }
+    }
+    out
+}
+
+#[track_caller]
+pub fn demangle_type(s: &str) -> Result<StringTypeReference, GenericError<ParseStrError>> {
+    let mut demangler = Demangler { rest: s, depth: 0 };
+    demangler
+        .eat(TYPE_SYMBOL_PREFIX)
+        .then(|| demangler.parse_type())
+        .flatten()
+        .filter(|_| demangler.rest.is_empty())
+        .ok_or(ParseStrError::AtMangledName(s.into()).throw())
+}
+
+#[track_caller]
+pub fn demangle_method(
+    s: &str,
+) -> Result<(StringTypeReference, StringMethodReference), GenericError<ParseStrError>> {
+    let mut demangler = Demangler { rest: s, depth: 0 };
+    demangler
+        .eat(METHOD_SYMBOL_PREFIX)
+        .then(|| demangler.parse_method())
+        .flatten()
+        .filter(|_| demangler.rest.is_empty())
+        .ok_or(ParseStrError::AtMangledName(s.into()).throw())
+}
+
+fn write_count(out: &mut String, count: usize) {
+    write!(out, "{count}_").unwrap();
+}
+
+fn write_ident(out: &mut String, name: &str) {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' => escaped.push(c),
+            '_' => escaped.push_str("__"),
+            _ => write!(escaped, "_{:x}_", c as u32).unwrap(),
+        }
+    }
+    write_count(out, escaped.len());
+    out.push_str(&escaped);
+}
+
+fn write_type_vars(out: &mut String, type_vars: &IndexMap<StringName, StringTypeReference>) {
+    write_count(out, type_vars.len());
+    for (k, v) in type_vars.iter() {
+        write_ident(out, k);
+        write_type(out, v);
+    }
+}
+
+fn write_type(out: &mut String, ty: &StringTypeReference) {
+    match ty {
+        StringTypeReference::Single { assem, ty } => {
+            out.push('S');
+            write_ident(out, assem);
+            write_ident(out, ty);
+        }
+        StringTypeReference::Generic(name) => {
+            out.push('G');
+            write_ident(out, name);
+        }
+        StringTypeReference::WithGeneric {
+            assem,
+            ty,
+            type_vars,
+        } => {
+            out.push('W');
+            write_ident(out, assem);
+            write_ident(out, ty);
+            write_type_vars(out, type_vars);
+        }
+        StringTypeReference::Array(elem) => {
+            out.push('A');
+            write_type(out, elem);
+        }
+        StringTypeReference::Nullable(elem) => {
+            out.push('N');
+            write_type(out, elem);
+        }
+        StringTypeReference::Pointer(elem) => {
+            out.push('P');
+            write_type(out, elem);
+        }
+        StringTypeReference::Tuple(elems) => {
+            out.push('T');
+            write_count(out, elems.len());
+            elems.iter().for_each(|x| write_type(out, x));
+        }
+        StringTypeReference::Function { params, ret } => {
+            out.push('F');
+            write_count(out, params.len());
+            params.iter().for_each(|x| write_type(out, x));
+            write_type(out, ret);
+        }
+    }
+}
+
+struct Demangler<'a> {
+    rest: &'a str,
+    depth: usize,
+}
+
+impl<'a> Demangler<'a> {
+    fn eat(&mut self, prefix: &str) -> bool {
+        match self.rest.strip_prefix(prefix) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+    fn take(&mut self, len: usize) -> Option<&'a str> {
+        if !self.rest.is_char_boundary(len) || len > self.rest.len() {
+            return None;
+        }
+        let (taken, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(taken)
+    }
+    fn count(&mut self) -> Option<usize> {
+        let digits = self.rest.find(|c: char| !c.is_ascii_digit())?;
+        let count = self.take(digits)?.parse().ok()?;
+        self.eat("_").then_some(count)
+    }
+    fn ident(&mut self) -> Option<StringName> {
+        let len = self.count()?;
+        let mut escaped = self.take(len)?;
+        let mut name = String::with_capacity(len);
+        while let Some(i) = escaped.find('_') {
+            name.push_str(&escaped[..i]);
+            escaped = &escaped[i + 1..];
+            if let Some(rest) = escaped.strip_prefix('_') {
+                name.push('_');
+                escaped = rest;
+            } else {
+                let (hex, rest) = escaped.split_once('_')?;
+                name.push(char::from_u32(u32::from_str_radix(hex, 16).ok()?)?);
+                escaped = rest;
+            }
+        }
+        name.push_str(escaped);
+        Some(StringName::from_string(name))
+    }
+    fn tag(&mut self) -> Option<char> {
+        let c = self.rest.chars().next()?;
+        self.rest = &self.rest[c.len_utf8()..];
+        Some(c)
+    }
+    fn type_vars(&mut self) -> Option<IndexMap<StringName, StringTypeReference>> {
+        let count = self.count()?;
+        let mut type_vars = IndexMap::new();
+        for _ in 0..count {
+            let k = self.ident()?;
+            // a repeated name would demangle to a different type than was mangled
+            if type_vars.insert(k, self.parse_type()?).is_some() {
+                return None;
+            }
+        }
+        Some(type_vars)
+    }
+    fn types(&mut self) -> Option<Vec<StringTypeReference>> {
+        let count = self.count()?;
+        (0..count).map(|_| self.parse_type()).collect()
+    }
+    fn parse_type(&mut self) -> Option<StringTypeReference> {
+        if self.depth >= MAX_TYPE_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let ty = self.parse_nested_type();
+        self.depth -= 1;
+        ty
+    }
+    fn parse_nested_type(&mut self) -> Option<StringTypeReference> {
+        Some(match self.tag()? {
+            'S' => StringTypeReference::Single {
+                assem: self.ident()?,
+                ty: self.ident()?,
+            },
+            'G' => StringTypeReference::Generic(self.ident()?),
+            'W' => StringTypeReference::WithGeneric {
+                assem: self.ident()?,
+                ty: self.ident()?,
+                type_vars: Arc::new(self.type_vars()?),
+            },
+            'A' => StringTypeReference::make_array(self.parse_type()?),
+            'N' => StringTypeReference::make_nullable(self.parse_type()?),
+            'P' => StringTypeReference::make_pointer(self.parse_type()?),
+            'T' => StringTypeReference::make_tuple(self.types()?),
+            'F' => StringTypeReference::make_function(self.types()?, self.parse_type()?),
+            _ => return None,
+        })
+    }
+    fn parse_method(&mut self) -> Option<(StringTypeReference, StringMethodReference)> {
+        let owner = self.parse_type()?;
+        let method = match self.tag()? {
+            'S' => StringMethodReference::Single(self.ident()?),
+            'W' => StringMethodReference::WithGeneric(self.ident()?, Arc::new(self.type_vars()?)),
+            _ => return None,
+        };
+        Some((owner, method))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift64, enough to explore the grammar deterministically.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+        fn below(&mut self, n: u64) -> usize {
+            (self.next() % n) as usize
+        }
+        fn name(&mut self) -> StringName {
+            const CHARS: &[char] = &[
+                'a', 'Z', '0', '9', '_', '.', '+', '@', '[', ']', '(', ')', ',', '|', ':', '!',
+                ' ', 'é', '字', '🦀',
+            ];
+            let len = self.below(8);
+            StringName::from_string(
+                (0..len)
+                    .map(|_| CHARS[self.below(CHARS.len() as u64)])
+                    .collect(),
+            )
+        }
+        fn type_vars(&mut self, depth: u32) -> IndexMap<StringName, StringTypeReference> {
+            (0..self.below(3))
+                .map(|_| (self.name(), self.ty(depth + 1)))
+                .collect()
+        }
+        fn ty(&mut self, depth: u32) -> StringTypeReference {
+            let kind = if depth > 3 {
+                self.below(2)
+            } else {
+                self.below(8)
+            };
+            match kind {
+                0 => StringTypeReference::Single {
+                    assem: self.name(),
+                    ty: self.name(),
+                },
+                1 => StringTypeReference::Generic(self.name()),
+                2 => StringTypeReference::WithGeneric {
+                    assem: self.name(),
+                    ty: self.name(),
+                    type_vars: Arc::new(self.type_vars(depth)),
+                },
+                3 => StringTypeReference::make_array(self.ty(depth + 1)),
+                4 => StringTypeReference::make_nullable(self.ty(depth + 1)),
+                5 => StringTypeReference::make_pointer(self.ty(depth + 1)),
+                6 => StringTypeReference::make_tuple(
+                    (0..self.below(3))
+                        .map(|_| self.ty(depth + 1))
+                        .collect::<Vec<_>>(),
+                ),
+                _ => StringTypeReference::make_function(
+                    (0..self.below(3))
+                        .map(|_| self.ty(depth + 1))
+                        .collect::<Vec<_>>(),
+                    self.ty(depth + 1),
+                ),
+            }
+        }
+    }
+
+    fn is_linker_safe(s: &str) -> bool {
+        s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    #[test]
+    fn test_mangle_round_trip_fuzz() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..2000 {
+            let ty = rng.ty(0);
+            let mangled = mangle_type(&ty);
+            assert!(is_linker_safe(&mangled), "{mangled}");
+            assert_eq!(demangle_type(&mangled).unwrap(), ty, "{mangled}");
+
+            let method = if rng.below(2) == 0 {
+                StringMethodReference::Single(rng.name())
+            } else {
+                StringMethodReference::WithGeneric(rng.name(), Arc::new(rng.type_vars(0)))
+            };
+            let mangled = mangle_method(&ty, &method);
+            assert!(is_linker_safe(&mangled), "{mangled}");
+            assert_eq!(
+                demangle_method(&mangled).unwrap(),
+                (ty, method),
+                "{mangled}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_mangle_examples() {
+        let ty = StringTypeReference::from_string_repr("[!]System.UInt8[]").unwrap();
+        assert_eq!(mangle_type(&ty), "_PLTAS4__21_15_System_2e_UInt8");
+        let method = StringMethodReference::static_single("Main()");
+        assert_eq!(
+            mangle_method(&crate::core_types::OBJECT, &method),
+            "_PLMS4__21_16_System_2e_ObjectS12_Main_28__29_"
+        );
+        for s in [
+            "",
+            "_PLT",
+            "_PLTX",
+            "_PLTS4__21_",
+            "_PLTS99_a",
+            "_PLTG3__zz_",
+            "_PLTW1_A1_B2_1_KS1_A1_B1_KS1_A1_B",
+        ] {
+            assert!(demangle_type(s).is_err(), "{s}");
+        }
+        assert!(demangle_type("_PLTW1_A1_B2_1_KS1_A1_B1_VS1_A1_B").is_ok());
+        let deep = "_PLT".to_owned() + &"A".repeat(MAX_TYPE_DEPTH - 1) + "G1_T";
+        assert!(demangle_type(&deep).is_ok());
+        assert!(demangle_type(&("_PLT".to_owned() + &"A".repeat(1_000_000))).is_err());
+    }
+}
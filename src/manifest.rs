@@ -0,0 +1,295 @@
+//! Assembly versions, the assemblies they reference, and resolution of those references.
+
+use crate::StringName;
+use crate::container::ContentHash;
+use crate::errors::{GenericError, ResolveError};
+use borsh::{BorshDeserialize, BorshSerialize};
+use derive_ctor::ctor;
+use getset::Getters;
+use indexmap::IndexMap;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Borsh encoding of `semver` types as their string form.
+pub(crate) mod borsh_semver {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use std::fmt::Display;
+    use std::io::{Error, ErrorKind, Read, Result, Write};
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, W: Write>(x: &T, writer: &mut W) -> Result<()> {
+        x.to_string().serialize(writer)
+    }
+
+    pub fn deserialize<T, R>(reader: &mut R) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+        R: Read,
+    {
+        String::deserialize_reader(reader)?
+            .parse()
+            .map_err(|e: T::Err| Error::new(ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    ctor,
+    Getters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[getset(get = "pub")]
+pub struct AssemblyIdentity {
+    name: StringName,
+    #[borsh(
+        serialize_with = "borsh_semver::serialize",
+        deserialize_with = "borsh_semver::deserialize"
+    )]
+    version: Version,
+    /// Only known for an assembly that has been written, see [`ContentHash`].
+    #[ctor(default)]
+    content_hash: Option<ContentHash>,
+}
+
+impl AssemblyIdentity {
+    pub fn with_content_hash(mut self, hash: ContentHash) -> Self {
+        self.content_hash = Some(hash);
+        self
+    }
+}
+
+impl Display for AssemblyIdentity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.version)?;
+        match &self.content_hash {
+            Some(hash) => write!(f, " ({hash})"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A referenced assembly and the versions of it that are acceptable.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    ctor,
+    Getters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[getset(get = "pub")]
+pub struct AssemblyReference {
+    name: StringName,
+    #[borsh(
+        serialize_with = "borsh_semver::serialize",
+        deserialize_with = "borsh_semver::deserialize"
+    )]
+    req: VersionReq,
+}
+
+impl AssemblyReference {
+    pub fn matches(&self, identity: &AssemblyIdentity) -> bool {
+        self.name == identity.name && self.req.matches(&identity.version)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, ctor, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Manifest {
+    identity: AssemblyIdentity,
+    references: Vec<AssemblyReference>,
+}
+
+impl Manifest {
+    pub fn reference(&self, name: &str) -> Option<&AssemblyReference> {
+        self.references.iter().find(|x| x.name == *name)
+    }
+}
+
+/// Who asked for which versions of an assembly, as reported by [`ResolveError`].
+#[derive(Clone, Debug, PartialEq, Eq, ctor, Getters)]
+#[getset(get = "pub")]
+pub struct Requirement {
+    required_by: StringName,
+    req: VersionReq,
+}
+
+impl Display for Requirement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} requires {}", self.required_by, self.req)
+    }
+}
+
+/// Picks one version of every assembly a root manifest depends on, among the manifests
+/// of the available assemblies.
+#[derive(Default)]
+pub struct Resolver {
+    candidates: IndexMap<StringName, Vec<Manifest>>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add_candidate(&mut self, manifest: Manifest) -> &mut Self {
+        self.candidates
+            .entry(manifest.identity.name.clone())
+            .or_default()
+            .push(manifest);
+        self
+    }
+    pub fn candidates(&self, name: &str) -> &[Manifest] {
+        self.candidates.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Resolves the references of `root` and, transitively, of everything it selects,
+    /// preferring the highest version that meets every requirement seen so far.
+    ///
+    /// Requirements are only ever added: when a newly selected assembly constrains one
+    /// selected earlier, resolution starts over with the larger set. It does not
+    /// backtrack, so a conflict may be reported where dropping a requirement of an
+    /// abandoned candidate would have succeeded.
+    #[track_caller]
+    pub fn resolve(
+        &self,
+        root: &Manifest,
+    ) -> Result<IndexMap<StringName, AssemblyIdentity>, GenericError<ResolveError>> {
+        let mut requirements = IndexMap::<StringName, Vec<Requirement>>::new();
+        add_requirements(&mut requirements, root);
+        'restart: loop {
+            let mut selected = IndexMap::<StringName, &Manifest>::new();
+            let mut i = 0;
+            while i < requirements.len() {
+                let (name, reqs) = requirements.get_index(i).unwrap();
+                let chosen = self.select(root, name, reqs)?;
+                selected.insert(name.clone(), chosen);
+                if add_requirements(&mut requirements, chosen)
+                    && selected.iter().any(|(name, x)| {
+                        !requirements[name]
+                            .iter()
+                            .all(|r| r.req.matches(&x.identity.version))
+                    })
+                {
+                    continue 'restart;
+                }
+                i += 1;
+            }
+            return Ok(selected
+                .into_iter()
+                .map(|(name, x)| (name, x.identity.clone()))
+                .collect());
+        }
+    }
+
+    #[track_caller]
+    fn select<'s>(
+        &'s self,
+        root: &'s Manifest,
+        name: &StringName,
+        reqs: &[Requirement],
+    ) -> Result<&'s Manifest, GenericError<ResolveError>> {
+        let candidates = match root.identity.name == *name {
+            true => std::slice::from_ref(root),
+            false => self.candidates(name),
+        };
+        if candidates.is_empty() {
+            return Err(ResolveError::NotFound {
+                name: name.clone(),
+                required_by: reqs.to_vec(),
+            }
+            .throw());
+        }
+        candidates
+            .iter()
+            .filter(|x| reqs.iter().all(|r| r.req.matches(&x.identity.version)))
+            .max_by(|a, b| a.identity.version.cmp(&b.identity.version))
+            .ok_or_else(|| {
+                let mut available = candidates
+                    .iter()
+                    .map(|x| x.identity.version.clone())
+                    .collect::<Vec<_>>();
+                available.sort();
+                ResolveError::Conflict {
+                    name: name.clone(),
+                    requirements: reqs.to_vec(),
+                    available,
+                }
+                .throw()
+            })
+    }
+}
+
+/// Records the references of `manifest`, returning whether any of them was new.
+fn add_requirements(
+    requirements: &mut IndexMap<StringName, Vec<Requirement>>,
+    manifest: &Manifest,
+) -> bool {
+    let mut changed = false;
+    for reference in &manifest.references {
+        let requirement = Requirement::new(manifest.identity.name.clone(), reference.req.clone());
+        let list = requirements.entry(reference.name.clone()).or_default();
+        if !list.contains(&requirement) {
+            list.push(requirement);
+            changed = true;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(name: &str, version: &str, references: &[(&str, &str)]) -> Manifest {
+        Manifest::new(
+            AssemblyIdentity::new(StringName::from(name), version.parse().unwrap()),
+            references
+                .iter()
+                .map(|(name, req)| {
+                    AssemblyReference::new(StringName::from(*name), req.parse().unwrap())
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut resolver = Resolver::new();
+        resolver
+            .add_candidate(manifest("Core", "1.0.0", &[]))
+            .add_candidate(manifest("Core", "1.4.2", &[]))
+            .add_candidate(manifest("Core", "2.0.0", &[]))
+            .add_candidate(manifest("Json", "0.3.0", &[("Core", ">=1.2, <2")]))
+            .add_candidate(manifest("Http", "1.1.0", &[("Core", "^2")]));
+
+        let app = manifest("App", "0.1.0", &[("Json", "0.3"), ("Core", "^1")]);
+        let resolved = resolver.resolve(&app).unwrap();
+        assert_eq!(resolved["Core"].version(), &Version::new(1, 4, 2));
+        assert_eq!(resolved["Json"].version(), &Version::new(0, 3, 0));
+
+        let app = manifest("App", "0.1.0", &[("Json", "0.3"), ("Http", "1")]);
+        let e = resolver.resolve(&app).unwrap_err().into_error();
+        assert!(matches!(&e, ResolveError::Conflict { name, .. } if name == "Core"));
+        assert_eq!(
+            e.to_string(),
+            "Conflict(Core: Json requires >=1.2, <2, Http requires ^2; available: 1.0.0, 1.4.2, 2.0.0)"
+        );
+
+        let app = manifest("App", "0.1.0", &[("Xml", "*")]);
+        let e = resolver.resolve(&app).unwrap_err().into_error();
+        assert!(matches!(e, ResolveError::NotFound { .. }));
+    }
+}
@@ -0,0 +1,361 @@
+//! Definitions of assemblies and their members, shared by the compiler, the VM and tools.
+
+use crate::attrs::{
+    ConstValue, CustomAttribute, FieldAttr, HasCustomAttributes, MethodAttr, TypeAttr,
+};
+use crate::errors::{AttrError, GenericError};
+use crate::generics::{GenericParamDef, HasGenericParams};
+use crate::instruction::StringInstruction;
+use crate::manifest::{AssemblyIdentity, AssemblyReference, Manifest, borsh_semver};
+use crate::{QualifiedName, StringMethodReference, StringName, StringTypeReference};
+use bon::Builder;
+use borsh::{BorshDeserialize, BorshSerialize};
+use getset::{Getters, MutGetters};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Builder,
+    Getters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[getset(get = "pub", get_mut = "pub")]
+pub struct FieldDef {
+    #[builder(into)]
+    name: StringName,
+    attr: FieldAttr,
+    ty: StringTypeReference,
+    /// Set exactly for fields with the `Const` flag.
+    const_value: Option<ConstValue>,
+    #[builder(default)]
+    #[getset(skip)]
+    custom_attrs: Vec<CustomAttribute>,
+}
+
+impl FieldDef {
+    /// Rejects contradictory flags and a `Const` flag without value (or the reverse).
+    #[track_caller]
+    pub fn validate(&self) -> Result<(), GenericError<AttrError>> {
+        self.attr.validate()?;
+        match (self.attr.is_const(), &self.const_value) {
+            (true, None) => Err(AttrError::MissingConstValue.throw()),
+            (false, Some(_)) => Err(AttrError::UnexpectedConstValue.throw()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Builder,
+    Getters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[getset(get = "pub", get_mut = "pub")]
+pub struct ParamDef {
+    #[builder(into)]
+    name: StringName,
+    ty: StringTypeReference,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Builder,
+    Getters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[getset(get = "pub", get_mut = "pub")]
+pub struct MethodSignature {
+    #[builder(default)]
+    params: Vec<ParamDef>,
+    #[builder(default = crate::core_types::VOID)]
+    ret_type: StringTypeReference,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Builder,
+    Getters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[getset(get = "pub", get_mut = "pub")]
+pub struct MethodDef {
+    /// Name including the parameter list, as used by [`StringMethodReference`].
+    #[builder(into)]
+    name: StringName,
+    attr: MethodAttr,
+    signature: MethodSignature,
+    /// Empty for methods without a body, e.g. abstract or native ones.
+    #[builder(default)]
+    body: Vec<StringInstruction>,
+    #[builder(default)]
+    #[getset(skip)]
+    custom_attrs: Vec<CustomAttribute>,
+    #[builder(default)]
+    #[getset(skip)]
+    generic_params: Vec<GenericParamDef>,
+}
+
+impl MethodDef {
+    pub fn reference(&self) -> StringMethodReference {
+        StringMethodReference::Single(self.name.clone())
+    }
+    pub fn has_body(&self) -> bool {
+        !self.body.is_empty()
+    }
+}
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Builder,
+    Getters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[getset(get = "pub", get_mut = "pub")]
+pub struct TypeDef {
+    /// Qualified name without assembly, e.g. `System.Console+KeyInfo`.
+    #[builder(into)]
+    name: StringName,
+    attr: TypeAttr,
+    parent: Option<StringTypeReference>,
+    #[builder(default)]
+    interfaces: Vec<StringTypeReference>,
+    /// In instance layout order.
+    #[builder(default)]
+    fields: Vec<FieldDef>,
+    /// Their order carries no meaning: methods are referenced by name, never by position.
+    #[builder(default)]
+    methods: Vec<MethodDef>,
+    #[builder(default)]
+    #[getset(skip)]
+    custom_attrs: Vec<CustomAttribute>,
+    #[builder(default)]
+    #[getset(skip)]
+    generic_params: Vec<GenericParamDef>,
+}
+
+impl TypeDef {
+    pub fn qualified_name(&self) -> QualifiedName {
+        QualifiedName::new(self.name.clone())
+    }
+    pub fn field(&self, name: &str) -> Option<&FieldDef> {
+        self.fields.iter().find(|x| x.name == *name)
+    }
+    pub fn method(&self, name: &str) -> Option<&MethodDef> {
+        self.methods.iter().find(|x| x.name == *name)
+    }
+    /// Sorts the methods by name and canonicalizes the custom attributes of the type and
+    /// its members. Fields keep their order, which determines the instance layout.
+    pub fn canonicalize(&mut self) {
+        self.methods.sort_by(|a, b| a.name.cmp(&b.name));
+        self.custom_attrs
+            .iter_mut()
+            .chain(self.fields.iter_mut().flat_map(|x| &mut x.custom_attrs))
+            .chain(self.methods.iter_mut().flat_map(|x| &mut x.custom_attrs))
+            .for_each(CustomAttribute::canonicalize);
+    }
+    #[track_caller]
+    pub fn validate(&self) -> Result<(), GenericError<AttrError>> {
+        self.attr.validate()?;
+        self.generic_params
+            .iter()
+            .try_for_each(GenericParamDef::validate)?;
+        self.fields.iter().try_for_each(FieldDef::validate)?;
+        self.methods.iter().try_for_each(|x| {
+            x.attr.validate(x.has_body())?;
+            x.generic_params
+                .iter()
+                .try_for_each(GenericParamDef::validate)
+        })
+    }
+}
+
+macro impl_has_custom_attributes($($t:ty),*) {
+    $(
+        impl HasCustomAttributes for $t {
+            fn custom_attrs(&self) -> &[CustomAttribute] {
+                &self.custom_attrs
+            }
+            fn custom_attrs_mut(&mut self) -> &mut Vec<CustomAttribute> {
+                &mut self.custom_attrs
+            }
+        }
+    )*
+}
+
+impl_has_custom_attributes!(FieldDef, MethodDef, TypeDef);
+
+macro impl_has_generic_params($($t:ty),*) {
+    $(
+        impl HasGenericParams for $t {
+            fn generic_params(&self) -> &[GenericParamDef] {
+                &self.generic_params
+            }
+            fn generic_params_mut(&mut self) -> &mut Vec<GenericParamDef> {
+                &mut self.generic_params
+            }
+        }
+    )*
+}
+
+impl_has_generic_params!(MethodDef, TypeDef);
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Builder,
+    Getters,
+    MutGetters,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[getset(get = "pub", get_mut = "pub")]
+pub struct AssemblyDef {
+    #[builder(into)]
+    name: StringName,
+    #[builder(default = Version::new(0, 0, 0))]
+    #[borsh(
+        serialize_with = "borsh_semver::serialize",
+        deserialize_with = "borsh_semver::deserialize"
+    )]
+    version: Version,
+    #[builder(default)]
+    references: Vec<AssemblyReference>,
+    #[builder(default)]
+    types: Vec<TypeDef>,
+}
+
+impl AssemblyDef {
+    pub fn identity(&self) -> AssemblyIdentity {
+        AssemblyIdentity::new(self.name.clone(), self.version.clone())
+    }
+    pub fn manifest(&self) -> Manifest {
+        Manifest::new(self.identity(), self.references.clone())
+    }
+    pub fn find_type(&self, name: &str) -> Option<&TypeDef> {
+        self.types.iter().find(|x| x.name == *name)
+    }
+    /// Reference to a type of this assembly.
+    pub fn type_ref(&self, ty: &TypeDef) -> StringTypeReference {
+        StringTypeReference::Single {
+            assem: self.name.clone(),
+            ty: ty.name.clone(),
+        }
+    }
+    /// Puts the assembly in the order its encoding is reproducible in: types sorted by
+    /// name, see [`TypeDef::canonicalize`] for their members.
+    pub fn canonicalize(&mut self) {
+        self.types.sort_by(|a, b| a.name.cmp(&b.name));
+        self.types.iter_mut().for_each(TypeDef::canonicalize);
+    }
+    #[track_caller]
+    pub fn validate(&self) -> Result<(), GenericError<AttrError>> {
+        self.types.iter().try_for_each(TypeDef::validate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attrs::{FieldImplementationFlags, Visibility};
+    use crate::{core_types, fixtures};
+
+    fn program() -> AssemblyDef {
+        let mut assembly =
+            fixtures::program(vec![StringInstruction::ReturnVal { register_addr: 0 }]);
+        let counter = FieldDef::builder()
+            .name("counter")
+            .attr(FieldAttr::new(
+                Visibility::Private,
+                FieldImplementationFlags::Static.into(),
+            ))
+            .ty(core_types::UINT64)
+            .build();
+        assembly.types_mut()[0].fields_mut().push(counter);
+        assembly
+    }
+
+    #[test]
+    fn test_assembly_def() {
+        let deprecated = StringTypeReference::make_static_single("Lib", "DeprecatedAttribute");
+        let mut assembly = program();
+        assembly.types_mut()[0].methods_mut()[0].add_custom_attr(CustomAttribute::new(
+            deprecated.clone(),
+            vec![ConstValue::String(StringName::from("use X"))],
+            [(StringName::from("error"), ConstValue::Boolean(true))].into(),
+        ));
+        assembly.validate().unwrap();
+        let program = assembly.find_type("App.Program").unwrap();
+        let attr = program
+            .method("Main()")
+            .unwrap()
+            .find_custom_attr(&deprecated);
+        assert_eq!(
+            attr.unwrap().named_arg("error"),
+            Some(&ConstValue::Boolean(true))
+        );
+        assert_eq!(
+            assembly.type_ref(program).string_name_repr().as_str(),
+            "[App]App.Program"
+        );
+        assert_eq!(program.field("counter").unwrap().ty(), &core_types::UINT64);
+        assert_eq!(
+            program.method("Main()").unwrap().signature().ret_type(),
+            &core_types::VOID
+        );
+
+        let decoded = borsh::from_slice::<AssemblyDef>(&borsh::to_vec(&assembly).unwrap()).unwrap();
+        assert_eq!(decoded, assembly);
+
+        let mut broken = assembly.clone();
+        broken.types_mut()[0].methods_mut()[0].body_mut().clear();
+        assert!(broken.validate().is_err());
+
+        let mut answer = FieldDef::builder()
+            .name("Answer")
+            .attr(FieldAttr::new_const(Visibility::Public))
+            .ty(core_types::INT32)
+            .const_value(ConstValue::Int32(42))
+            .build();
+        answer.validate().unwrap();
+        *answer.const_value_mut() = None;
+        assert!(answer.validate().is_err());
+        let mut counter = assembly.types()[0].fields()[0].clone();
+        *counter.const_value_mut() = Some(ConstValue::Null);
+        assert!(counter.validate().is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Upgrades of assembly files written in older format versions.
+//!
+//! The header and directory of every supported version are read directly; a migration
+//! step is only needed when the content of a section changes, e.g. when the
+//! `StringInstructionType` opcodes are renumbered. Steps run on the raw sections, in
+//! version order, before the file is parsed into the current model.
+
+use crate::container::{
+    Directory, FORMAT_VERSION, METHOD_RECORD_LEN, Records, SectionKind, write_sections,
+};
+use crate::errors::{BinaryError, GenericError};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// Upgrades the sections of a file from one format version to the next.
+pub type MigrationFn = fn(&mut Sections) -> Result<(), GenericError<BinaryError>>;
+
+/// The sections of a file being upgraded, in directory order.
+pub struct Sections {
+    sections: Vec<(SectionKind, Vec<u8>)>,
+}
+
+impl Sections {
+    #[track_caller]
+    fn new(directory: &Directory) -> Result<Self, GenericError<BinaryError>> {
+        Ok(Self {
+            sections: directory
+                .contents()?
+                .into_iter()
+                .map(|(kind, bytes)| (kind, bytes.into_owned()))
+                .collect(),
+        })
+    }
+    pub fn get(&self, kind: SectionKind) -> Option<&[u8]> {
+        self.sections
+            .iter()
+            .find(|x| x.0 == kind)
+            .map(|x| x.1.as_slice())
+    }
+    pub fn get_mut(&mut self, kind: SectionKind) -> Option<&mut Vec<u8>> {
+        self.sections
+            .iter_mut()
+            .find(|x| x.0 == kind)
+            .map(|x| &mut x.1)
+    }
+    /// Replaces the section of that kind, or appends it.
+    pub fn insert(&mut self, kind: SectionKind, bytes: Vec<u8>) {
+        match self.get_mut(kind) {
+            Some(x) => *x = bytes,
+            None => self.sections.push((kind, bytes)),
+        }
+    }
+    pub fn remove(&mut self, kind: SectionKind) -> Option<Vec<u8>> {
+        let index = self.sections.iter().position(|x| x.0 == kind)?;
+        Some(self.sections.remove(index).1)
+    }
+    #[track_caller]
+    fn section(&self, kind: SectionKind) -> Result<&[u8], GenericError<BinaryError>> {
+        self.get(kind).ok_or(BinaryError::SectionNotFound.throw())
+    }
+
+    /// Replaces every method body with `f(body)` and moves the code offsets in the method
+    /// records accordingly. Expects the method records of the current version.
+    #[track_caller]
+    pub fn rewrite_bodies(
+        &mut self,
+        mut f: impl FnMut(&[u8]) -> Result<Vec<u8>, GenericError<BinaryError>>,
+    ) -> Result<(), GenericError<BinaryError>> {
+        let code = self.section(SectionKind::Code)?.to_vec();
+        let count = Records::parse(self.section(SectionKind::Methods)?, METHOD_RECORD_LEN)?.len();
+        let methods = self.get_mut(SectionKind::Methods).unwrap();
+        let mut new_code = Vec::with_capacity(code.len());
+        for i in 0..count {
+            // `code_offset` and `code_len` end the record
+            let at = 4 + (i + 1) * METHOD_RECORD_LEN - 8;
+            let offset = u32::from_le_bytes(methods[at..at + 4].try_into().unwrap()) as usize;
+            let len = u32::from_le_bytes(methods[at + 4..at + 8].try_into().unwrap()) as usize;
+            let body = match len {
+                0 => Vec::new(),
+                _ => f(code
+                    .get(offset..offset + len)
+                    .ok_or(BinaryError::IndexOutOfRange.throw())?)?,
+            };
+            methods[at..at + 4].copy_from_slice(&(new_code.len() as u32).to_le_bytes());
+            methods[at + 4..at + 8].copy_from_slice(&(body.len() as u32).to_le_bytes());
+            new_code.extend_from_slice(&body);
+        }
+        self.insert(SectionKind::Code, new_code);
+        Ok(())
+    }
+}
+
+/// Migration steps, keyed by the version they upgrade from.
+#[derive(Clone)]
+pub struct MigrationRegistry {
+    steps: BTreeMap<u16, MigrationFn>,
+}
+
+impl Default for MigrationRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl MigrationRegistry {
+    pub fn empty() -> Self {
+        Self {
+            steps: BTreeMap::new(),
+        }
+    }
+    /// The steps between the versions this crate has written. Version 1 only lacks the
+    /// checksums of version 2, so there are none yet.
+    pub fn builtin() -> Self {
+        Self::empty()
+    }
+    /// Registers the step from `from` to `from + 1`, replacing any previous one.
+    #[track_caller]
+    pub fn register(
+        &mut self,
+        from: u16,
+        step: MigrationFn,
+    ) -> Result<&mut Self, GenericError<BinaryError>> {
+        if !(1..FORMAT_VERSION).contains(&from) {
+            return Err(BinaryError::NoMigrationFrom(from).throw());
+        }
+        self.steps.insert(from, step);
+        Ok(self)
+    }
+
+    /// Returns `bytes` unchanged if they are in the current format version, and otherwise
+    /// runs every step from their version on and re-encodes them in the current one.
+    /// Checksums are not verified.
+    #[track_caller]
+    pub fn upgrade<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, [u8]>, GenericError<BinaryError>> {
+        let directory = Directory::parse(bytes)?;
+        if directory.version() == FORMAT_VERSION {
+            return Ok(Cow::Borrowed(bytes));
+        }
+        self.rewrite(&directory).map(Cow::Owned)
+    }
+    /// Re-encodes the file in the current format version with every section decompressed,
+    /// running the steps from its version on.
+    #[track_caller]
+    pub(crate) fn rewrite(
+        &self,
+        directory: &Directory,
+    ) -> Result<Vec<u8>, GenericError<BinaryError>> {
+        let mut sections = Sections::new(directory)?;
+        self.steps
+            .range(directory.version()..)
+            .try_for_each(|(_, step)| step(&mut sections))?;
+        let mut out = Vec::new();
+        write_sections(&sections.sections, &mut out).unwrap();
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::{
+        WriteOptions, encode_assembly, read_assembly, read_assembly_with, write_sections_as,
+    };
+    use crate::fixtures;
+    use crate::instruction::StringInstruction;
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    /// An opcode table from before `Load_u64` and `ReturnVal` swapped places.
+    #[derive(BorshSerialize, BorshDeserialize)]
+    #[allow(non_camel_case_types)]
+    enum OldInstruction {
+        ReturnVal { register_addr: u64 },
+        Load_u64 { register_addr: u64, val: u64 },
+    }
+
+    fn renumber(body: &[u8]) -> Result<Vec<u8>, GenericError<BinaryError>> {
+        let old = borsh::from_slice::<Vec<OldInstruction>>(body)
+            .map_err(|_| BinaryError::WrongFileFormat.throw())?;
+        let new = old
+            .into_iter()
+            .map(|x| match x {
+                OldInstruction::ReturnVal { register_addr } => {
+                    StringInstruction::ReturnVal { register_addr }
+                }
+                OldInstruction::Load_u64 { register_addr, val } => {
+                    StringInstruction::Load_u64 { register_addr, val }
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(borsh::to_vec(&new).unwrap())
+    }
+
+    fn old_numbering(body: &[u8]) -> Result<Vec<u8>, GenericError<BinaryError>> {
+        let new = borsh::from_slice::<Vec<StringInstruction>>(body).unwrap();
+        let old = new
+            .into_iter()
+            .map(|x| match x {
+                StringInstruction::ReturnVal { register_addr } => {
+                    OldInstruction::ReturnVal { register_addr }
+                }
+                StringInstruction::Load_u64 { register_addr, val } => {
+                    OldInstruction::Load_u64 { register_addr, val }
+                }
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        Ok(borsh::to_vec(&old).unwrap())
+    }
+
+    #[test]
+    fn test_migration() {
+        let assembly = fixtures::program(vec![
+            StringInstruction::Load_u64 {
+                register_addr: 0,
+                val: 42,
+            },
+            StringInstruction::ReturnVal { register_addr: 0 },
+        ]);
+        let bytes = encode_assembly(&assembly);
+        let registry = MigrationRegistry::builtin();
+        assert!(matches!(
+            registry.upgrade(&bytes).unwrap(),
+            Cow::Borrowed(_)
+        ));
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            read_assembly(&newer).unwrap_err().into_error().into_root(),
+            BinaryError::UnsupportedVersion { found, .. } if found == FORMAT_VERSION + 1
+        ));
+
+        // a version 1 file written with the old opcode table
+        let directory = Directory::parse(&bytes).unwrap();
+        let mut sections = Sections::new(&directory).unwrap();
+        sections.rewrite_bodies(old_numbering).unwrap();
+        let mut v1 = Vec::new();
+        write_sections_as(1, &sections.sections, WriteOptions::default(), &mut v1).unwrap();
+        assert_ne!(read_assembly(&v1).ok(), Some(assembly.clone()));
+
+        let mut registry = MigrationRegistry::empty();
+        let step: MigrationFn = |sections| sections.rewrite_bodies(renumber);
+        assert!(registry.register(FORMAT_VERSION, step).is_err());
+        registry.register(1, step).unwrap();
+        assert_eq!(registry.upgrade(&v1).unwrap().as_ref(), bytes.as_slice());
+        assert_eq!(read_assembly_with(&v1, &registry).unwrap(), assembly);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::StringName;
+use proc_macros::ThreadSafe;
+use std::fmt::{Debug, Display};
+
+/// A type name such as `System.Collections.List` or `System.Console+KeyInfo`.
+///
+/// Segments are separated by [`QualifiedName::NAMESPACE_SEPARATOR`] up to the outermost
+/// type and by [`QualifiedName::NESTED_SEPARATOR`] for nested types.
+#[repr(transparent)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, ThreadSafe)]
+pub struct QualifiedName {
+    name: StringName,
+}
+
+impl QualifiedName {
+    pub const NAMESPACE_SEPARATOR: char = '.';
+    pub const NESTED_SEPARATOR: char = '+';
+
+    pub const fn new(name: StringName) -> Self {
+        Self { name }
+    }
+    pub const fn from_static_str(s: &'static str) -> Self {
+        Self::new(StringName::from_static_str(s))
+    }
+    pub fn as_str(&self) -> &str {
+        self.name.as_str()
+    }
+    pub fn as_string_name(&self) -> &StringName {
+        &self.name
+    }
+    pub fn into_string_name(self) -> StringName {
+        self.name
+    }
+    fn is_separator(c: char) -> bool {
+        c == Self::NAMESPACE_SEPARATOR || c == Self::NESTED_SEPARATOR
+    }
+}
+
+impl QualifiedName {
+    /// All namespace and type segments, outermost first.
+    pub fn segments(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.as_str().split(Self::is_separator)
+    }
+    /// Namespace of the outermost type, `None` for names without one.
+    pub fn namespace(&self) -> Option<&str> {
+        let outermost = self.outermost_type_str();
+        outermost
+            .rfind(Self::NAMESPACE_SEPARATOR)
+            .map(|i| &outermost[..i])
+    }
+    pub fn simple_name(&self) -> &str {
+        let s = self.as_str();
+        s.rfind(Self::is_separator).map_or(s, |i| &s[i + 1..])
+    }
+    pub fn is_nested(&self) -> bool {
+        self.as_str().contains(Self::NESTED_SEPARATOR)
+    }
+    /// The type a nested type is declared in.
+    pub fn declaring_type(&self) -> Option<QualifiedName> {
+        let s = self.as_str();
+        s.rfind(Self::NESTED_SEPARATOR).map(|i| Self::from(&s[..i]))
+    }
+    pub fn outermost_type(&self) -> QualifiedName {
+        if self.is_nested() {
+            Self::from(self.outermost_type_str())
+        } else {
+            self.clone()
+        }
+    }
+    fn outermost_type_str(&self) -> &str {
+        let s = self.as_str();
+        s.split_once(Self::NESTED_SEPARATOR).map_or(s, |(x, _)| x)
+    }
+}
+
+impl QualifiedName {
+    /// Appends a namespace or type segment.
+    pub fn join<T: AsRef<str>>(&self, segment: T) -> Self {
+        self.join_with(Self::NAMESPACE_SEPARATOR, segment.as_ref())
+    }
+    /// Appends a nested type segment.
+    pub fn join_nested<T: AsRef<str>>(&self, segment: T) -> Self {
+        self.join_with(Self::NESTED_SEPARATOR, segment.as_ref())
+    }
+    fn join_with(&self, separator: char, segment: &str) -> Self {
+        if self.as_str().is_empty() {
+            return Self::from(segment);
+        }
+        Self::from_string(format!("{}{separator}{segment}", self.as_str()))
+    }
+    fn from_string(s: String) -> Self {
+        Self::new(StringName::from_string(s))
+    }
+    /// Segment-wise prefix check: `System.Col` is not a prefix of `System.Collections`,
+    /// and `System+Console` is not one of `System.Console`.
+    pub fn starts_with(&self, prefix: &QualifiedName) -> bool {
+        if prefix.as_str().is_empty() {
+            return true;
+        }
+        self.as_str()
+            .strip_prefix(prefix.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(Self::is_separator))
+    }
+}
+
+impl Debug for QualifiedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        <StringName as Debug>::fmt(&self.name, f)
+    }
+}
+
+impl Display for QualifiedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        <StringName as Display>::fmt(&self.name, f)
+    }
+}
+
+impl From<StringName> for QualifiedName {
+    fn from(value: StringName) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for QualifiedName {
+    fn from(value: &str) -> Self {
+        Self::new(StringName::from(value))
+    }
+}
+
+impl From<QualifiedName> for StringName {
+    fn from(value: QualifiedName) -> Self {
+        value.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qualified_name() {
+        let name = QualifiedName::from("System.Collections.List+Enumerator");
+        assert_eq!(
+            name.segments().collect::<Vec<_>>(),
+            ["System", "Collections", "List", "Enumerator"]
+        );
+        assert_eq!(name.namespace(), Some("System.Collections"));
+        assert_eq!(name.simple_name(), "Enumerator");
+        assert_eq!(
+            name.declaring_type(),
+            Some(QualifiedName::from("System.Collections.List"))
+        );
+        assert!(name.starts_with(&QualifiedName::from("System.Collections")));
+        assert!(!name.starts_with(&QualifiedName::from("System.Col")));
+        assert!(name.starts_with(&QualifiedName::from("System.Collections.List")));
+        assert!(!name.starts_with(&QualifiedName::from("System.Collections+List")));
+        assert_eq!(
+            QualifiedName::from("System")
+                .join("Console")
+                .join_nested("KeyInfo"),
+            QualifiedName::from("System.Console+KeyInfo")
+        );
+        assert_eq!(QualifiedName::from("Object").namespace(), None);
+    }
+}
@@ -0,0 +1,170 @@
+//! Checks that assemblies are built reproducibly.
+//!
+//! Containers hold no timestamps or other per-build data and follow the order of the
+//! definitions, so two [`AssemblyDef`]s that are equal after
+//! [`AssemblyDef::canonicalize`] encode to the same bytes.
+
+use crate::container::{Directory, WriteOptions, write_assembly_with};
+use crate::errors::{GenericError, ReproducibilityError};
+use crate::metadata::AssemblyDef;
+
+/// Encodes a canonicalized copy of `assembly`.
+pub fn canonical_bytes(assembly: &AssemblyDef, options: WriteOptions) -> Vec<u8> {
+    let mut assembly = assembly.clone();
+    assembly.canonicalize();
+    let mut out = Vec::new();
+    write_assembly_with(&assembly, options, &mut out).unwrap();
+    out
+}
+
+/// Runs `build` twice and returns the canonical bytes if both runs produced them.
+#[track_caller]
+pub fn check_reproducible(
+    options: WriteOptions,
+    mut build: impl FnMut() -> AssemblyDef,
+) -> Result<Vec<u8>, GenericError<ReproducibilityError>> {
+    let first = canonical_bytes(&build(), options);
+    let second = canonical_bytes(&build(), options);
+    compare(&first, &second)?;
+    Ok(first)
+}
+
+/// Locates the first byte where `actual` differs from `expected`, e.g. a checked-in
+/// golden file. Differences in the sections are reported before those in the header,
+/// whose content hash and directory follow from them.
+#[track_caller]
+pub fn compare(expected: &[u8], actual: &[u8]) -> Result<(), GenericError<ReproducibilityError>> {
+    if expected == actual {
+        return Ok(());
+    }
+    let first_difference = |from: usize| {
+        let (expected, actual) = (expected.get(from..)?, actual.get(from..)?);
+        match expected.iter().zip(actual).position(|(a, b)| a != b) {
+            Some(offset) => Some(from + offset),
+            None if expected.len() == actual.len() => None,
+            None => Some(from + expected.len().min(actual.len())),
+        }
+    };
+    let directory = Directory::parse(expected).ok();
+    let sections_start = directory
+        .as_ref()
+        .and_then(|x| x.entries().iter().map(|x| x.offset()).min())
+        .unwrap_or(0) as usize;
+    let offset = first_difference(sections_start)
+        .or_else(|| first_difference(0))
+        .unwrap() as u64;
+    let section = directory.and_then(|x| {
+        x.entries()
+            .iter()
+            .find(|x| (x.offset()..x.offset() + x.length()).contains(&offset))
+            .map(|x| x.kind())
+    });
+    Err(ReproducibilityError::BytesDiffer { offset, section }.throw())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StringName;
+    use crate::attrs::{
+        ConstValue, CustomAttribute, FieldAttr, FieldImplementationFlags, HasCustomAttributes,
+        Visibility,
+    };
+    use crate::container::SectionKind;
+    use crate::metadata::FieldDef;
+    use crate::{StringTypeReference, instruction::StringInstruction};
+    use crate::{core_types, fixtures};
+    use std::collections::HashMap;
+
+    /// Stands in for a compiler that collects types and attribute arguments in hash maps,
+    /// whose iteration order changes from one run to the next.
+    fn compile_fixture() -> AssemblyDef {
+        let named_args = ["Name", "Order", "Hidden"]
+            .into_iter()
+            .zip([
+                ConstValue::Int32(1),
+                ConstValue::Int32(2),
+                ConstValue::Boolean(true),
+            ])
+            .collect::<HashMap<_, _>>();
+        let mut marker = CustomAttribute::new(
+            StringTypeReference::make_static_single("App", "App.Marker"),
+            Vec::new(),
+            Default::default(),
+        );
+        named_args.into_iter().for_each(|(name, value)| {
+            marker
+                .named_args_mut()
+                .insert(StringName::from(name), value);
+        });
+
+        let types = ["App.Program", "App.Util", "App.Model", "App.Marker"]
+            .into_iter()
+            .map(|name| (name, ()))
+            .collect::<HashMap<_, _>>();
+        let types = types
+            .into_keys()
+            .map(|name| {
+                let methods = ["Run()", "Main()", "Stop()"]
+                    .into_iter()
+                    .map(|x| (x, ()))
+                    .collect::<HashMap<_, _>>()
+                    .into_keys()
+                    .map(|name| {
+                        fixtures::static_method(
+                            name,
+                            vec![StringInstruction::ReturnVal { register_addr: 0 }],
+                        )
+                    })
+                    .collect();
+                let mut ty = fixtures::static_class(name, methods);
+                ty.fields_mut().push(
+                    FieldDef::builder()
+                        .name("count")
+                        .attr(FieldAttr::new(
+                            Visibility::Private,
+                            FieldImplementationFlags::Static.into(),
+                        ))
+                        .ty(core_types::UINT64)
+                        .build(),
+                );
+                ty.add_custom_attr(marker.clone());
+                ty
+            })
+            .collect();
+        AssemblyDef::builder().name("App").types(types).build()
+    }
+
+    #[test]
+    fn test_reproducible_build() {
+        let options = WriteOptions::builder().compress_sections(true).build();
+        let bytes = check_reproducible(options, compile_fixture).unwrap();
+        for _ in 0..8 {
+            compare(&bytes, &canonical_bytes(&compile_fixture(), options)).unwrap();
+        }
+
+        let mut changed = compile_fixture();
+        *changed.types_mut()[0].fields_mut()[0].name_mut() = StringName::from("total");
+        let plain = canonical_bytes(&compile_fixture(), WriteOptions::default());
+        let e = compare(&plain, &canonical_bytes(&changed, WriteOptions::default()))
+            .unwrap_err()
+            .into_error();
+        assert!(matches!(
+            e,
+            ReproducibilityError::BytesDiffer {
+                section: Some(_),
+                ..
+            }
+        ));
+        let e = compare(&plain, &plain[..plain.len() - 1])
+            .unwrap_err()
+            .into_error();
+        assert!(matches!(
+            e,
+            ReproducibilityError::BytesDiffer {
+                section: Some(SectionKind::Manifest),
+                ..
+            }
+        ));
+    }
+}
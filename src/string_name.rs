@@ -1,7 +1,10 @@
+use crate::symbol::{Symbol, SymbolTable};
+use borsh::{BorshDeserialize, BorshSerialize};
 use derive_more::Deref;
 use faststr::FastStr;
 use proc_macros::ThreadSafe;
 use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
 use std::ops::Add;
 use std::sync::Arc;
 use std::{
@@ -9,18 +12,24 @@ use std::{
     str::{FromStr, pattern::Pattern},
 };
 
-#[repr(transparent)]
-#[derive(Clone, PartialEq, Eq, Hash, Deref, Default, ThreadSafe)]
+#[derive(Clone, Deref, Default, ThreadSafe)]
 pub struct StringName {
+    #[deref]
     s: FastStr,
+    /// Set only for names interned in the global [`SymbolTable`].
+    sym: Option<Symbol>,
 }
 
 impl StringName {
     pub const fn from_static_str(s: &'static str) -> Self {
         Self {
             s: FastStr::from_static_str(s),
+            sym: None,
         }
     }
+    const fn from_fast_str(s: FastStr) -> Self {
+        Self { s, sym: None }
+    }
     pub fn as_str(&self) -> &str {
         self.s.as_str()
     }
@@ -28,20 +37,35 @@ impl StringName {
         self.s.as_bytes().to_vec()
     }
     pub fn from_arc_str(s: Arc<str>) -> Self {
-        Self {
-            s: FastStr::from_arc_str(s),
-        }
+        Self::from_fast_str(FastStr::from_arc_str(s))
     }
     pub fn from_arc_string(s: Arc<String>) -> Self {
-        Self {
-            s: FastStr::from_arc_string(s),
-        }
+        Self::from_fast_str(FastStr::from_arc_string(s))
     }
     pub fn from_string(s: String) -> Self {
-        Self {
-            s: FastStr::from_string(s),
+        Self::from_fast_str(FastStr::from_string(s))
+    }
+}
+
+impl StringName {
+    /// Returns the shared, interned copy of `s`; equality between interned names
+    /// compares their [`Symbol`]s only.
+    pub fn intern<T: AsRef<str>>(s: T) -> Self {
+        let (sym, s) = SymbolTable::intern_global(s.as_ref());
+        Self { s, sym: Some(sym) }
+    }
+    pub fn interned(&self) -> Self {
+        match self.sym {
+            Some(_) => self.clone(),
+            None => Self::intern(self.as_str()),
         }
     }
+    pub fn symbol(&self) -> Option<Symbol> {
+        self.sym
+    }
+    pub fn is_interned(&self) -> bool {
+        self.sym.is_some()
+    }
 }
 
 impl StringName {
@@ -50,6 +74,35 @@ impl StringName {
     }
 }
 
+impl PartialEq for StringName {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.sym, other.sym) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.s == other.s,
+        }
+    }
+}
+
+impl Eq for StringName {}
+
+impl PartialOrd for StringName {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StringName {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for StringName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.s.hash(state)
+    }
+}
+
 impl Debug for StringName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         <FastStr as Debug>::fmt(&self.s, f)
@@ -70,17 +123,13 @@ impl Borrow<str> for StringName {
 
 impl PartialEq<str> for StringName {
     fn eq(&self, other: &str) -> bool {
-        self.eq(&StringName {
-            s: FastStr::from_string(other.to_owned()),
-        })
+        self.as_str() == other
     }
 }
 
 impl<T: AsRef<str>> From<T> for StringName {
     fn from(value: T) -> Self {
-        StringName {
-            s: FastStr::from_str(value.as_ref()).unwrap(),
-        }
+        Self::from_fast_str(FastStr::from_str(value.as_ref()).unwrap())
     }
 }
 
@@ -88,8 +137,30 @@ impl<T: AsRef<str>> Add<T> for StringName {
     type Output = Self;
     fn add(self, rhs: T) -> Self::Output {
         let rhs = rhs.as_ref();
-        Self {
-            s: FastStr::from_string(self.s.to_string() + rhs),
-        }
+        Self::from_fast_str(FastStr::from_string(self.s.to_string() + rhs))
+    }
+}
+
+impl serde::Serialize for StringName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for StringName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <String as serde::Deserialize>::deserialize(deserializer).map(Self::from_string)
+    }
+}
+
+impl BorshSerialize for StringName {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        BorshSerialize::serialize(self.as_str(), writer)
+    }
+}
+
+impl BorshDeserialize for StringName {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        String::deserialize_reader(reader).map(Self::from_string)
     }
 }
@@ -1,12 +1,23 @@
 use crate::errors::{GenericError, ParseStrError};
-use crate::{StringName, string_name};
+use crate::{QualifiedName, StringName, string_name};
+use borsh::{BorshDeserialize, BorshSerialize};
 use derive_more::Unwrap;
 use indexmap::IndexMap;
 use proc_macros::ThreadSafe;
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
 use std::sync::LazyLock;
 use std::{hash::Hash, sync::Arc};
 
+/// String syntax:
+/// - `[assem]ty` single type, `@T` generic variable
+/// - `[assem]ty[K:V|K2:V2]` generic instance
+/// - `T[]` array, `T?` nullable, `&T` pointer
+/// - `(A,B)` tuple (`(A,)` for one element), `fn(A,B)->R` function
+///
+/// Pointers and functions take everything after them as their operand, so an array of
+/// them is written with a grouping paren, e.g. `(&[!]A)[]`.
 #[derive(Debug, Clone, PartialEq, Eq, ThreadSafe)]
 pub enum StringTypeReference {
     Single {
@@ -19,18 +30,25 @@ pub enum StringTypeReference {
         ty: StringName,
         type_vars: Arc<IndexMap<StringName, StringTypeReference>>,
     },
+    Array(Arc<StringTypeReference>),
+    Nullable(Arc<StringTypeReference>),
+    Pointer(Arc<StringTypeReference>),
+    Tuple(Arc<[StringTypeReference]>),
+    Function {
+        params: Arc<[StringTypeReference]>,
+        ret: Arc<StringTypeReference>,
+    },
 }
 
 impl Hash for StringTypeReference {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.tag().hash(state);
         match self {
             StringTypeReference::Single { assem, ty } => {
-                0u8.hash(state);
                 assem.hash(state);
                 ty.hash(state);
             }
             StringTypeReference::Generic(string_name) => {
-                1u8.hash(state);
                 string_name.hash(state);
             }
             StringTypeReference::WithGeneric {
@@ -38,18 +56,110 @@ impl Hash for StringTypeReference {
                 ty,
                 type_vars,
             } => {
-                2u8.hash(state);
                 assem.hash(state);
                 ty.hash(state);
-                for (k, v) in type_vars.iter() {
-                    k.hash(state);
-                    v.hash(state);
-                }
+                sorted_type_vars(type_vars).hash(state);
+            }
+            StringTypeReference::Array(elem) => {
+                elem.hash(state);
+            }
+            StringTypeReference::Nullable(elem) => {
+                elem.hash(state);
+            }
+            StringTypeReference::Pointer(elem) => {
+                elem.hash(state);
+            }
+            StringTypeReference::Tuple(elems) => {
+                elems.hash(state);
+            }
+            StringTypeReference::Function { params, ret } => {
+                params.hash(state);
+                ret.hash(state);
             }
         }
     }
 }
 
+/// `type_vars` maps compare equal regardless of insertion order, so hashing and ordering
+/// go through their entries sorted by name.
+fn sorted_type_vars(
+    type_vars: &IndexMap<StringName, StringTypeReference>,
+) -> Vec<(&StringName, &StringTypeReference)> {
+    let mut entries = type_vars.iter().collect::<Vec<_>>();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+impl PartialOrd for StringTypeReference {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StringTypeReference {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use StringTypeReference::*;
+        self.tag()
+            .cmp(&other.tag())
+            .then_with(|| match (self, other) {
+                (
+                    Single { assem, ty },
+                    Single {
+                        assem: other_assem,
+                        ty: other_ty,
+                    },
+                ) => assem.cmp(other_assem).then_with(|| ty.cmp(other_ty)),
+                (Generic(a), Generic(b)) => a.cmp(b),
+                (
+                    WithGeneric {
+                        assem,
+                        ty,
+                        type_vars,
+                    },
+                    WithGeneric {
+                        assem: other_assem,
+                        ty: other_ty,
+                        type_vars: other_type_vars,
+                    },
+                ) => assem
+                    .cmp(other_assem)
+                    .then_with(|| ty.cmp(other_ty))
+                    .then_with(|| {
+                        sorted_type_vars(type_vars).cmp(&sorted_type_vars(other_type_vars))
+                    }),
+                (Array(a), Array(b)) | (Nullable(a), Nullable(b)) | (Pointer(a), Pointer(b)) => {
+                    a.cmp(b)
+                }
+                (Tuple(a), Tuple(b)) => a.cmp(b),
+                (
+                    Function { params, ret },
+                    Function {
+                        params: other_params,
+                        ret: other_ret,
+                    },
+                ) => params.cmp(other_params).then_with(|| ret.cmp(other_ret)),
+                _ => unreachable!(),
+            })
+    }
+}
+
+impl StringTypeReference {
+    /// Variant tag: hashed and borsh-encoded before the payload, and compared first by
+    /// `Ord`. Decoding in `read_type` matches on the same values.
+    const fn tag(&self) -> u8 {
+        match self {
+            Self::Single { .. } => 0,
+            Self::Generic(_) => 1,
+            Self::WithGeneric { .. } => 2,
+            Self::Array(_) => 3,
+            Self::Nullable(_) => 4,
+            Self::Pointer(_) => 5,
+            Self::Tuple(_) => 6,
+            Self::Function { .. } => 7,
+        }
+    }
+}
+
 impl StringTypeReference {
     pub const CORE_ASSEMBLY_NAME: StringName = string_name!("!");
 
@@ -86,63 +196,258 @@ impl StringTypeReference {
             ty: StringName::from_static_str(ty),
         }
     }
+
+    pub fn make_array(elem: StringTypeReference) -> Self {
+        Self::Array(Arc::new(elem))
+    }
+    pub fn make_nullable(elem: StringTypeReference) -> Self {
+        Self::Nullable(Arc::new(elem))
+    }
+    pub fn make_pointer(elem: StringTypeReference) -> Self {
+        Self::Pointer(Arc::new(elem))
+    }
+    pub fn make_tuple<I: IntoIterator<Item = StringTypeReference>>(elems: I) -> Self {
+        Self::Tuple(elems.into_iter().collect())
+    }
+    pub fn make_function<I: IntoIterator<Item = StringTypeReference>>(
+        params: I,
+        ret: StringTypeReference,
+    ) -> Self {
+        Self::Function {
+            params: params.into_iter().collect(),
+            ret: Arc::new(ret),
+        }
+    }
 }
 
 impl StringTypeReference {
     pub fn is_generic(&self) -> bool {
         matches!(self, Self::Generic(_))
     }
+    pub fn is_array(&self) -> bool {
+        matches!(self, Self::Array(_))
+    }
+    pub fn is_nullable(&self) -> bool {
+        matches!(self, Self::Nullable(_))
+    }
+    pub fn is_pointer(&self) -> bool {
+        matches!(self, Self::Pointer(_))
+    }
+    /// Element of an array, nullable or pointer type.
+    pub fn element_type(&self) -> Option<&StringTypeReference> {
+        match self {
+            Self::Array(elem) | Self::Nullable(elem) | Self::Pointer(elem) => Some(elem),
+            _ => None,
+        }
+    }
+    /// Replaces every generic variable bound in `type_vars` (keyed by the full variable name,
+    /// e.g. `@T`); unbound ones are kept.
+    pub fn substitute(&self, type_vars: &IndexMap<StringName, StringTypeReference>) -> Self {
+        let substitute_all = |elems: &[StringTypeReference]| {
+            elems
+                .iter()
+                .map(|x| x.substitute(type_vars))
+                .collect::<Vec<_>>()
+        };
+        match self {
+            Self::Single { .. } => self.clone(),
+            Self::Generic(name) => type_vars.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Self::WithGeneric {
+                assem,
+                ty,
+                type_vars: vars,
+            } => Self::WithGeneric {
+                assem: assem.clone(),
+                ty: ty.clone(),
+                type_vars: Arc::new(
+                    vars.iter()
+                        .map(|(k, v)| (k.clone(), v.substitute(type_vars)))
+                        .collect(),
+                ),
+            },
+            Self::Array(elem) => Self::make_array(elem.substitute(type_vars)),
+            Self::Nullable(elem) => Self::make_nullable(elem.substitute(type_vars)),
+            Self::Pointer(elem) => Self::make_pointer(elem.substitute(type_vars)),
+            Self::Tuple(elems) => Self::make_tuple(substitute_all(elems)),
+            Self::Function { params, ret } => {
+                Self::make_function(substitute_all(params), ret.substitute(type_vars))
+            }
+        }
+    }
+    /// Replaces every assembly name found in `renames`, e.g. when modules are linked into
+    /// one assembly.
+    pub fn rename_assemblies(&self, renames: &IndexMap<StringName, StringName>) -> Self {
+        let rename = |assem: &StringName| renames.get(assem).unwrap_or(assem).clone();
+        let rename_all = |elems: &[StringTypeReference]| {
+            elems
+                .iter()
+                .map(|x| x.rename_assemblies(renames))
+                .collect::<Vec<_>>()
+        };
+        match self {
+            Self::Single { assem, ty } => Self::Single {
+                assem: rename(assem),
+                ty: ty.clone(),
+            },
+            Self::Generic(_) => self.clone(),
+            Self::WithGeneric {
+                assem,
+                ty,
+                type_vars,
+            } => Self::WithGeneric {
+                assem: rename(assem),
+                ty: ty.clone(),
+                type_vars: Arc::new(rename_type_vars(type_vars, renames)),
+            },
+            Self::Array(elem) => Self::make_array(elem.rename_assemblies(renames)),
+            Self::Nullable(elem) => Self::make_nullable(elem.rename_assemblies(renames)),
+            Self::Pointer(elem) => Self::make_pointer(elem.rename_assemblies(renames)),
+            Self::Tuple(elems) => Self::make_tuple(rename_all(elems)),
+            Self::Function { params, ret } => {
+                Self::make_function(rename_all(params), ret.rename_assemblies(renames))
+            }
+        }
+    }
+}
+
+fn rename_type_vars(
+    type_vars: &IndexMap<StringName, StringTypeReference>,
+    renames: &IndexMap<StringName, StringName>,
+) -> IndexMap<StringName, StringTypeReference> {
+    type_vars
+        .iter()
+        .map(|(k, v)| (k.clone(), v.rename_assemblies(renames)))
+        .collect()
+}
+
+/// Renames the assemblies of the parameter types in a method name such as
+/// `Foo([Lib]A,[!]B[])`. A name whose parameter list does not parse is kept as it is.
+pub(crate) fn rename_assemblies_in_method_name(
+    name: &StringName,
+    renames: &IndexMap<StringName, StringName>,
+) -> StringName {
+    let Some((method, params)) = name.as_str().split_once('(') else {
+        return name.clone();
+    };
+    let mut parser = TypeReprParser::new(params);
+    let Some(params) = parser.parse_list(")").filter(|_| parser.is_at_end()) else {
+        return name.clone();
+    };
+    let params = params
+        .iter()
+        .map(|x| x.rename_assemblies(renames))
+        .collect::<Vec<_>>();
+    let mut out = String::with_capacity(name.as_str().len());
+    out.push_str(method);
+    out.push('(');
+    write_list(&mut out, &params);
+    out.push(')');
+    StringName::from_string(out)
 }
 
 impl StringTypeReference {
     pub fn string_name_repr_without_assembly(&self) -> StringName {
         match self {
-            StringTypeReference::Single { assem: _, ty } => {
-                StringName::from(ty.as_str().to_string())
-            }
+            StringTypeReference::Single { .. } => self.qualified_name().unwrap().into(),
             StringTypeReference::Generic(s) => StringName::from(s.as_str()),
-            StringTypeReference::WithGeneric {
-                assem: _,
-                ty,
-                type_vars,
-            } => StringName::from(format!(
-                "{}[{}]",
-                ty.as_str(),
-                type_vars
-                    .iter()
-                    .map(|(n, a)| format!("{n}:{}", a.string_name_repr().as_str().to_owned()))
-                    .collect::<Vec<_>>()
-                    .join("|")
-            )),
+            _ => {
+                let mut s = String::new();
+                self.write_repr(&mut s, false);
+                StringName::from_string(s)
+            }
+        }
+    }
+    /// Name of a `Single` or `WithGeneric` type, without assembly and type vars.
+    pub fn qualified_name(&self) -> Option<QualifiedName> {
+        match self {
+            StringTypeReference::Single { ty, .. }
+            | StringTypeReference::WithGeneric { ty, .. } => Some(QualifiedName::new(ty.clone())),
+            _ => None,
         }
     }
+    pub fn namespace(&self) -> Option<StringName> {
+        self.qualified_name()?.namespace().map(StringName::from)
+    }
+    pub fn simple_name(&self) -> Option<StringName> {
+        self.qualified_name()
+            .map(|x| StringName::from(x.simple_name()))
+    }
     pub fn assembly_name(&self) -> Option<&StringName> {
         match self {
             StringTypeReference::Single { assem, .. } => Some(assem),
             StringTypeReference::Generic(_) => None,
             StringTypeReference::WithGeneric { assem, .. } => Some(assem),
+            StringTypeReference::Array(elem)
+            | StringTypeReference::Nullable(elem)
+            | StringTypeReference::Pointer(elem) => elem.assembly_name(),
+            StringTypeReference::Tuple(_) | StringTypeReference::Function { .. } => None,
         }
     }
     pub fn string_name_repr(&self) -> StringName {
+        match self {
+            StringTypeReference::Generic(s) => StringName::from(s.as_str()),
+            _ => {
+                let mut s = String::new();
+                self.write_repr(&mut s, true);
+                StringName::from_string(s)
+            }
+        }
+    }
+    fn write_repr(&self, out: &mut String, with_assembly: bool) {
         match self {
             StringTypeReference::Single { assem, ty } => {
-                StringName::from(format!("[{}]{}", assem.as_str(), ty.as_str()))
+                if with_assembly {
+                    write_assembly(out, assem);
+                }
+                out.push_str(ty.as_str());
             }
-            StringTypeReference::Generic(s) => StringName::from(s.as_str()),
+            StringTypeReference::Generic(s) => out.push_str(s.as_str()),
             StringTypeReference::WithGeneric {
                 assem,
                 ty,
                 type_vars,
-            } => StringName::from(format!(
-                "[{}]{}[{}]",
-                assem.as_str(),
-                ty.as_str(),
-                type_vars
-                    .iter()
-                    .map(|(n, a)| format!("{n}:{}", a.string_name_repr().as_str().to_owned()))
-                    .collect::<Vec<_>>()
-                    .join("|")
-            )),
+            } => {
+                if with_assembly {
+                    write_assembly(out, assem);
+                }
+                out.push_str(ty.as_str());
+                write_type_vars(out, type_vars);
+            }
+            StringTypeReference::Array(elem) => {
+                elem.write_suffix_operand(out, with_assembly);
+                out.push_str("[]");
+            }
+            StringTypeReference::Nullable(elem) => {
+                elem.write_suffix_operand(out, with_assembly);
+                out.push('?');
+            }
+            StringTypeReference::Pointer(elem) => {
+                out.push('&');
+                elem.write_repr(out, with_assembly);
+            }
+            StringTypeReference::Tuple(elems) => {
+                out.push('(');
+                write_list(out, elems);
+                if elems.len() == 1 {
+                    out.push(',');
+                }
+                out.push(')');
+            }
+            StringTypeReference::Function { params, ret } => {
+                out.push_str("fn(");
+                write_list(out, params);
+                out.push_str(")->");
+                ret.write_repr(out, true);
+            }
+        }
+    }
+    fn write_suffix_operand(&self, out: &mut String, with_assembly: bool) {
+        if matches!(self, Self::Pointer(_) | Self::Function { .. }) {
+            out.push('(');
+            self.write_repr(out, with_assembly);
+            out.push(')');
+        } else {
+            self.write_repr(out, with_assembly);
         }
     }
     #[track_caller]
@@ -150,55 +455,187 @@ impl StringTypeReference {
         s: T,
     ) -> crate::Result<Self, GenericError<ParseStrError>> {
         let s = s.as_ref();
-        if s.starts_with('@') {
-            Ok(Self::Generic(s.into()))
-        } else {
-            if !s.starts_with('[') {
-                return Err(ParseStrError::AtStringTypeReference(s.into()).throw());
-            }
-            let (assem, ty) = s
-                .split_once(']')
-                .ok_or(ParseStrError::AtStringTypeReference(s.into()).throw())?;
-            if ty.contains('[') {
-                if !ty.ends_with(']') {
-                    return Err(ParseStrError::AtStringTypeReference(s.into()).throw());
-                }
-                let (name, type_vars) = ty
-                    .split_once('[')
-                    .ok_or(ParseStrError::AtStringTypeReference(s.into()).throw())?;
-                let type_vars = type_vars[..(type_vars.len() - 1)]
-                    .split('|')
-                    .map(|x| x.split_once(":"))
-                    .map(|x| {
-                        let (k, v) = x?;
-                        Some((
-                            StringName::from(k),
-                            StringTypeReference::from_string_repr(v).ok()?,
-                        ))
-                    })
-                    .try_collect::<IndexMap<_, _>>()
-                    .ok_or(ParseStrError::AtStringTypeReference(s.into()).throw())?;
-                Ok(StringTypeReference::WithGeneric {
-                    assem: (&assem[1..]).into(),
-                    ty: name.into(),
-                    type_vars: Arc::new(type_vars),
-                })
+        let mut parser = TypeReprParser::new(s);
+        let ty = parser
+            .parse_type()
+            .ok_or(ParseStrError::AtStringTypeReference(s.into()).throw())?;
+        if !parser.is_at_end() {
+            return Err(ParseStrError::AtStringTypeReference(s.into()).throw());
+        }
+        Ok(ty)
+    }
+}
+
+fn write_assembly(out: &mut String, assem: &StringName) {
+    out.push('[');
+    out.push_str(assem.as_str());
+    out.push(']');
+}
+
+fn write_type_vars(out: &mut String, type_vars: &IndexMap<StringName, StringTypeReference>) {
+    out.push('[');
+    for (i, (n, a)) in type_vars.iter().enumerate() {
+        if i != 0 {
+            out.push('|');
+        }
+        out.push_str(n.as_str());
+        out.push(':');
+        a.write_repr(out, true);
+    }
+    out.push(']');
+}
+
+fn write_list(out: &mut String, elems: &[StringTypeReference]) {
+    for (i, x) in elems.iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        x.write_repr(out, true);
+    }
+}
+
+/// Recursive-descent parser for the syntax documented on [`StringTypeReference`].
+struct TypeReprParser<'a> {
+    rest: &'a str,
+    depth: usize,
+}
+
+impl<'a> TypeReprParser<'a> {
+    const NAME_END: &'static [char] = &['[', ']', '(', ')', '?', ',', '|', ':'];
+
+    fn new(s: &'a str) -> Self {
+        Self { rest: s, depth: 0 }
+    }
+    fn is_at_end(&self) -> bool {
+        self.rest.is_empty()
+    }
+    fn eat(&mut self, prefix: &str) -> bool {
+        match self.rest.strip_prefix(prefix) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+    fn expect(&mut self, prefix: &str) -> Option<()> {
+        self.eat(prefix).then_some(())
+    }
+    fn name(&mut self) -> Option<&'a str> {
+        let end = self.rest.find(Self::NAME_END).unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+        let (name, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(name)
+    }
+    fn parse_type(&mut self) -> Option<StringTypeReference> {
+        if self.depth >= MAX_TYPE_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let ty = self.parse_nested_type();
+        self.depth -= 1;
+        ty
+    }
+    fn parse_nested_type(&mut self) -> Option<StringTypeReference> {
+        if self.eat("&") {
+            return Some(StringTypeReference::make_pointer(self.parse_type()?));
+        }
+        if self.eat("fn(") {
+            let params = self.parse_list(")")?;
+            self.expect("->")?;
+            let ret = self.parse_type()?;
+            return Some(StringTypeReference::make_function(params, ret));
+        }
+        let mut ty = self.parse_atom()?;
+        for _ in self.depth..=MAX_TYPE_DEPTH {
+            if self.eat("[]") {
+                ty = StringTypeReference::make_array(ty);
+            } else if self.eat("?") {
+                ty = StringTypeReference::make_nullable(ty);
             } else {
-                Ok(Self::Single {
-                    assem: (&assem[1..]).into(),
-                    ty: ty.into(),
-                })
+                return Some(ty);
             }
         }
+        None
+    }
+    fn parse_atom(&mut self) -> Option<StringTypeReference> {
+        if self.rest.starts_with('@') {
+            return Some(StringTypeReference::Generic(self.name()?.into()));
+        }
+        if self.eat("(") {
+            if self.eat(")") {
+                return Some(StringTypeReference::make_tuple([]));
+            }
+            let first = self.parse_type()?;
+            if self.eat(")") {
+                // grouping paren
+                return Some(first);
+            }
+            self.expect(",")?;
+            let mut elems = vec![first];
+            if !self.eat(")") {
+                elems.extend(self.parse_list(")")?);
+            }
+            return Some(StringTypeReference::make_tuple(elems));
+        }
+        self.expect("[")?;
+        let (assem, rest) = self.rest.split_once(']')?;
+        self.rest = rest;
+        let ty = self.name()?;
+        if self.rest.starts_with('[') && !self.rest.starts_with("[]") {
+            let type_vars = self.parse_type_vars()?;
+            Some(StringTypeReference::WithGeneric {
+                assem: assem.into(),
+                ty: ty.into(),
+                type_vars: Arc::new(type_vars),
+            })
+        } else {
+            Some(StringTypeReference::Single {
+                assem: assem.into(),
+                ty: ty.into(),
+            })
+        }
+    }
+    /// Parses `A,B,...` followed by `close`; `close` alone gives an empty list.
+    fn parse_list(&mut self, close: &str) -> Option<Vec<StringTypeReference>> {
+        let mut elems = Vec::new();
+        if self.eat(close) {
+            return Some(elems);
+        }
+        loop {
+            elems.push(self.parse_type()?);
+            if self.eat(close) {
+                return Some(elems);
+            }
+            self.expect(",")?;
+        }
+    }
+    /// Parses `[K:V|K2:V2]`.
+    fn parse_type_vars(&mut self) -> Option<IndexMap<StringName, StringTypeReference>> {
+        self.expect("[")?;
+        let mut type_vars = IndexMap::new();
+        loop {
+            let k = self.name()?;
+            self.expect(":")?;
+            type_vars.insert(StringName::from(k), self.parse_type()?);
+            if self.eat("]") {
+                return Some(type_vars);
+            }
+            self.expect("|")?;
+        }
     }
 }
+
 impl Display for StringTypeReference {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.string_name_repr().as_str())
     }
 }
 
-#[derive(Unwrap, Clone, Debug)]
+#[derive(Unwrap, Clone, Debug, PartialEq, Eq)]
 pub enum StringMethodReference {
     /// e.g. A(), A(\[!\]A), A(\[!\]A,\[!\]B)
     /// No spaces around commas
@@ -206,6 +643,41 @@ pub enum StringMethodReference {
     WithGeneric(StringName, Arc<IndexMap<StringName, StringTypeReference>>),
 }
 
+impl Hash for StringMethodReference {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match self {
+            StringMethodReference::Single(name) => {
+                0u8.hash(state);
+                name.hash(state);
+            }
+            StringMethodReference::WithGeneric(name, type_vars) => {
+                1u8.hash(state);
+                name.hash(state);
+                sorted_type_vars(type_vars).hash(state);
+            }
+        }
+    }
+}
+
+impl PartialOrd for StringMethodReference {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StringMethodReference {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Single(a), Self::Single(b)) => a.cmp(b),
+            (Self::Single(_), Self::WithGeneric(..)) => Ordering::Less,
+            (Self::WithGeneric(..), Self::Single(_)) => Ordering::Greater,
+            (Self::WithGeneric(a, a_vars), Self::WithGeneric(b, b_vars)) => a
+                .cmp(b)
+                .then_with(|| sorted_type_vars(a_vars).cmp(&sorted_type_vars(b_vars))),
+        }
+    }
+}
+
 impl StringMethodReference {
     pub const STATIC_CTOR_REF: Self = Self::Single(string_name!(".sctor()"));
     pub const fn static_single(name: &'static str) -> Self {
@@ -226,6 +698,16 @@ impl StringMethodReference {
             )),
         }
     }
+    /// See [`StringTypeReference::rename_assemblies`].
+    pub fn rename_assemblies(&self, renames: &IndexMap<StringName, StringName>) -> Self {
+        match self {
+            Self::Single(name) => Self::Single(rename_assemblies_in_method_name(name, renames)),
+            Self::WithGeneric(name, type_vars) => Self::WithGeneric(
+                rename_assemblies_in_method_name(name, renames),
+                Arc::new(rename_type_vars(type_vars, renames)),
+            ),
+        }
+    }
     pub fn from_string_repr<T: AsRef<str>>(s: T) -> crate::Result<Self, crate::Error> {
         let s = s.as_ref();
         static REGEX: LazyLock<fancy_regex::Regex> = LazyLock::new(|| {
@@ -242,26 +724,14 @@ impl StringMethodReference {
             .name("Name")
             .ok_or(ParseStrError::AtStringMethodReference(s.into()).throw())?;
         if let Some(type_vars) = type_vars {
+            let mut parser = TypeReprParser::new(type_vars.as_str());
+            let type_vars = parser
+                .parse_type_vars()
+                .filter(|_| parser.is_at_end())
+                .ok_or(ParseStrError::AtStringMethodReference(s.into()).throw())?;
             Ok(Self::WithGeneric(
                 StringName::from(name.as_str()),
-                Arc::new(
-                    type_vars
-                        .as_str()
-                        .trim_start_matches('[')
-                        .trim_end_matches(']')
-                        .split('|')
-                        .map(|x| {
-                            if let Some((k, v)) = x.split_once(':') {
-                                Ok((
-                                    StringName::from(k),
-                                    StringTypeReference::from_string_repr(v)?,
-                                ))
-                            } else {
-                                Err(ParseStrError::AtStringMethodReference(s.into()).throw())
-                            }
-                        })
-                        .try_collect()?,
-                ),
+                Arc::new(type_vars),
             ))
         } else {
             Ok(Self::Single(StringName::from(name.as_str())))
@@ -274,3 +744,276 @@ impl Display for StringMethodReference {
         f.write_str(self.string_name_repr().as_str())
     }
 }
+
+macro impl_serde_by_string_repr($t:ty) {
+    impl serde::Serialize for $t {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.string_name_repr().as_str())
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for $t {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+            Self::from_string_repr(&s).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+impl_serde_by_string_repr!(StringTypeReference);
+impl_serde_by_string_repr!(StringMethodReference);
+
+fn invalid_tag(ty: &'static str, tag: u8) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("unexpected tag {tag} for {ty}"),
+    )
+}
+
+/// Nesting of array, nullable, pointer, tuple, function and generic types that
+/// [`BorshDeserialize`] and [`StringTypeReference::from_string_repr`] accept before
+/// rejecting the input, so that hostile input cannot exhaust the stack.
+pub const MAX_TYPE_DEPTH: usize = 64;
+
+fn read_type<R: Read>(reader: &mut R, depth: usize) -> std::io::Result<StringTypeReference> {
+    if depth > MAX_TYPE_DEPTH {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "StringTypeReference nested too deeply",
+        ));
+    }
+    let tag = u8::deserialize_reader(reader)?;
+    let depth = depth + 1;
+    Ok(match tag {
+        0 => StringTypeReference::Single {
+            assem: StringName::deserialize_reader(reader)?,
+            ty: StringName::deserialize_reader(reader)?,
+        },
+        1 => StringTypeReference::Generic(StringName::deserialize_reader(reader)?),
+        2 => StringTypeReference::WithGeneric {
+            assem: StringName::deserialize_reader(reader)?,
+            ty: StringName::deserialize_reader(reader)?,
+            type_vars: Arc::new(read_type_vars(reader, depth)?),
+        },
+        3 => StringTypeReference::make_array(read_type(reader, depth)?),
+        4 => StringTypeReference::make_nullable(read_type(reader, depth)?),
+        5 => StringTypeReference::make_pointer(read_type(reader, depth)?),
+        6 => StringTypeReference::make_tuple(read_types(reader, depth)?),
+        7 => StringTypeReference::make_function(
+            read_types(reader, depth)?,
+            read_type(reader, depth)?,
+        ),
+        _ => return Err(invalid_tag("StringTypeReference", tag)),
+    })
+}
+
+/// Layout of a borsh `Vec`.
+fn read_types<R: Read>(reader: &mut R, depth: usize) -> std::io::Result<Vec<StringTypeReference>> {
+    let len = u32::deserialize_reader(reader)?;
+    (0..len).map(|_| read_type(reader, depth)).collect()
+}
+
+/// Layout of a borsh `IndexMap`.
+fn read_type_vars<R: Read>(
+    reader: &mut R,
+    depth: usize,
+) -> std::io::Result<IndexMap<StringName, StringTypeReference>> {
+    let len = u32::deserialize_reader(reader)?;
+    (0..len)
+        .map(|_| {
+            Ok((
+                StringName::deserialize_reader(reader)?,
+                read_type(reader, depth)?,
+            ))
+        })
+        .collect()
+}
+
+impl BorshSerialize for StringTypeReference {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.tag().serialize(writer)?;
+        match self {
+            StringTypeReference::Single { assem, ty } => {
+                assem.serialize(writer)?;
+                ty.serialize(writer)
+            }
+            StringTypeReference::Generic(name) => name.serialize(writer),
+            StringTypeReference::WithGeneric {
+                assem,
+                ty,
+                type_vars,
+            } => {
+                assem.serialize(writer)?;
+                ty.serialize(writer)?;
+                type_vars.as_ref().serialize(writer)
+            }
+            StringTypeReference::Array(elem) => elem.as_ref().serialize(writer),
+            StringTypeReference::Nullable(elem) => elem.as_ref().serialize(writer),
+            StringTypeReference::Pointer(elem) => elem.as_ref().serialize(writer),
+            StringTypeReference::Tuple(elems) => elems.as_ref().serialize(writer),
+            StringTypeReference::Function { params, ret } => {
+                params.as_ref().serialize(writer)?;
+                ret.as_ref().serialize(writer)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for StringTypeReference {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        read_type(reader, 0)
+    }
+}
+
+impl BorshSerialize for StringMethodReference {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            StringMethodReference::Single(name) => {
+                0u8.serialize(writer)?;
+                name.serialize(writer)
+            }
+            StringMethodReference::WithGeneric(name, type_vars) => {
+                1u8.serialize(writer)?;
+                name.serialize(writer)?;
+                type_vars.as_ref().serialize(writer)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for StringMethodReference {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let tag = u8::deserialize_reader(reader)?;
+        Ok(match tag {
+            0 => Self::Single(StringName::deserialize_reader(reader)?),
+            1 => Self::WithGeneric(
+                StringName::deserialize_reader(reader)?,
+                Arc::new(read_type_vars(reader, 0)?),
+            ),
+            _ => return Err(invalid_tag("StringMethodReference", tag)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_repr_round_trip() {
+        for s in [
+            "[!]System.UInt8",
+            "@T",
+            "[!]System.UInt8[]",
+            "[!]System.UInt8[][]?",
+            "&[!]System.UInt8[]",
+            "(&[!]System.UInt8)[]",
+            "()",
+            "([!]A,)",
+            "([!]A,@T[],([!]B,[!]C))",
+            "fn()->()",
+            "fn([!]A,@T)->[!]B?",
+            "(fn([!]A)->[!]B)?",
+            "[Lib]List[T:[!]A[]]",
+            "[Lib]Map[K:[!]String|V:[Lib]List[T:([!]A,[!]B)]]?",
+        ] {
+            let ty = StringTypeReference::from_string_repr(s).unwrap();
+            assert_eq!(ty.string_name_repr().as_str(), s);
+        }
+        let array_of_ptr = StringTypeReference::make_array(StringTypeReference::make_pointer(
+            StringTypeReference::core_static_single_type("A"),
+        ));
+        assert_eq!(
+            StringTypeReference::from_string_repr(array_of_ptr.string_name_repr().as_str())
+                .unwrap(),
+            array_of_ptr
+        );
+        assert_eq!(
+            array_of_ptr.string_name_repr_without_assembly().as_str(),
+            "(&A)[]"
+        );
+        for s in ["", "[!]", "[!]A[", "([!]A", "fn([!]A)", "[!]A[T]", "[!]A)"] {
+            assert!(StringTypeReference::from_string_repr(s).is_err(), "{s}");
+        }
+        let deep = "&".repeat(MAX_TYPE_DEPTH - 1) + "[!]A";
+        assert!(StringTypeReference::from_string_repr(&deep).is_ok());
+        for s in [
+            "&".repeat(1_000_000) + "[!]A",
+            "(".repeat(1_000_000) + "[!]A",
+            "fn(".repeat(1_000_000),
+            "[!]A".to_owned() + &"[]".repeat(1_000_000),
+            "[L]B[T:".repeat(1_000_000),
+        ] {
+            assert!(StringTypeReference::from_string_repr(&s).is_err());
+        }
+    }
+
+    #[test]
+    fn test_method_repr_round_trip() {
+        let s = "Foo([!]A[],(@T,[!]B))[T:[Lib]List[T:[!]A]|U:[!]B?]";
+        let method = StringMethodReference::from_string_repr(s).unwrap();
+        assert!(
+            matches!(method, StringMethodReference::WithGeneric(_, ref vars) if vars.len() == 2)
+        );
+        assert_eq!(method.string_name_repr().as_str(), s);
+    }
+
+    #[test]
+    fn test_type_vars_order_insensitive() {
+        use std::collections::{BTreeSet, HashSet};
+        use std::hash::BuildHasher;
+
+        let a = StringTypeReference::from_string_repr("[Lib]Map[K:[!]A|V:[!]B]").unwrap();
+        let b = StringTypeReference::from_string_repr("[Lib]Map[V:[!]B|K:[!]A]").unwrap();
+        let c = StringTypeReference::from_string_repr("[Lib]Map[V:[!]A|K:[!]B]").unwrap();
+        let hasher = std::hash::RandomState::new();
+        assert_eq!(a, b);
+        assert_eq!(hasher.hash_one(&a), hasher.hash_one(&b));
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_ne!(a, c);
+        assert_ne!(a.cmp(&c), Ordering::Equal);
+        assert_eq!(HashSet::from([a.clone(), b.clone(), c.clone()]).len(), 2);
+        assert_eq!(BTreeSet::from([a, b, c]).len(), 2);
+
+        let m1 = StringMethodReference::from_string_repr("F()[T:[!]A|U:[!]B]").unwrap();
+        let m2 = StringMethodReference::from_string_repr("F()[U:[!]B|T:[!]A]").unwrap();
+        assert_eq!(m1, m2);
+        assert_eq!(hasher.hash_one(&m1), hasher.hash_one(&m2));
+        assert_eq!(m1.cmp(&m2), Ordering::Equal);
+        assert!(StringMethodReference::static_single("F()") < m1);
+    }
+
+    #[test]
+    fn test_serde_and_borsh() {
+        let ty = StringTypeReference::from_string_repr(
+            "[Lib]Map[K:[!]String|V:fn(@T,[!]A[])->([!]B,)?]",
+        )
+        .unwrap();
+        let json = serde_json::to_string(&ty).unwrap();
+        assert_eq!(json, format!("\"{ty}\""));
+        assert_eq!(
+            serde_json::from_str::<StringTypeReference>(&json).unwrap(),
+            ty
+        );
+        let nested = format!("\"{}[!]A\"", "&".repeat(1_000_000));
+        assert!(serde_json::from_str::<StringTypeReference>(&nested).is_err());
+        let bytes = borsh::to_vec(&ty).unwrap();
+        assert_eq!(
+            borsh::from_slice::<StringTypeReference>(&bytes).unwrap(),
+            ty
+        );
+        assert!(borsh::from_slice::<StringTypeReference>(&[8]).is_err());
+        // arrays of arrays, without ever reaching the element type
+        let nested = vec![3u8; 1_000_000];
+        assert!(borsh::from_slice::<StringTypeReference>(&nested).is_err());
+
+        let method = StringMethodReference::from_string_repr("Foo([!]A)[T:[!]B]").unwrap();
+        let bytes = borsh::to_vec(&method).unwrap();
+        assert_eq!(
+            borsh::from_slice::<StringMethodReference>(&bytes)
+                .unwrap()
+                .string_name_repr(),
+            method.string_name_repr()
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use crate::errors::{BinaryError, GenericError};
+use faststr::FastStr;
+use indexmap::IndexSet;
+use std::sync::{LazyLock, RwLock, RwLockReadGuard};
+
+/// Id of an interned string inside a [`SymbolTable`].
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub const fn from_index(index: u32) -> Self {
+        Self(index)
+    }
+    pub const fn index(self) -> u32 {
+        self.0
+    }
+}
+
+static GLOBAL_SYMBOLS: LazyLock<RwLock<SymbolTable>> =
+    LazyLock::new(|| RwLock::new(SymbolTable::new()));
+
+/// Insertion-ordered string table; a [`Symbol`] is the index of its string.
+///
+/// Section layout produced by [`SymbolTable::encode`] (all integers little-endian):
+/// `count: u32`, `count` end offsets as `u32`, then the concatenated UTF-8 bytes.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    strings: IndexSet<FastStr>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide table used by [`crate::StringName::intern`].
+    pub fn global() -> RwLockReadGuard<'static, SymbolTable> {
+        GLOBAL_SYMBOLS.read().unwrap()
+    }
+
+    pub(crate) fn intern_global(s: &str) -> (Symbol, FastStr) {
+        if let Some(found) = Self::global().get_full(s) {
+            return found;
+        }
+        let mut table = GLOBAL_SYMBOLS.write().unwrap();
+        let sym = table.intern(s);
+        (sym, table.strings[sym.0 as usize].clone())
+    }
+
+    fn get_full(&self, s: &str) -> Option<(Symbol, FastStr)> {
+        let (index, s) = self.strings.get_full(s)?;
+        Some((Symbol(index as u32), s.clone()))
+    }
+
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(index) = self.strings.get_index_of(s) {
+            return Symbol(index as u32);
+        }
+        let (index, _) = self.strings.insert_full(FastStr::new(s));
+        Symbol(index as u32)
+    }
+
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.strings.get_index_of(s).map(|x| Symbol(x as u32))
+    }
+
+    pub fn resolve(&self, sym: Symbol) -> Option<&str> {
+        self.strings.get_index(sym.0 as usize).map(FastStr::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+        self.strings
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (Symbol(i as u32), s.as_str()))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let data_len = self.strings.iter().map(|x| x.len()).sum::<usize>();
+        let mut out = Vec::with_capacity(4 + self.strings.len() * 4 + data_len);
+        out.extend_from_slice(&(self.strings.len() as u32).to_le_bytes());
+        let mut end = 0u32;
+        for s in self.strings.iter() {
+            end += s.len() as u32;
+            out.extend_from_slice(&end.to_le_bytes());
+        }
+        for s in self.strings.iter() {
+            out.extend_from_slice(s.as_bytes());
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, GenericError<BinaryError>> {
+        let read_u32 = |at: usize| -> Result<u32, GenericError<BinaryError>> {
+            let b = bytes
+                .get(at..at + 4)
+                .ok_or(BinaryError::BinaryTooShort.throw())?;
+            Ok(u32::from_le_bytes(b.try_into().unwrap()))
+        };
+        let count = read_u32(0)? as usize;
+        let data = bytes
+            .get(4 + count * 4..)
+            .ok_or(BinaryError::BinaryTooShort.throw())?;
+        let mut table = Self::new();
+        let mut start = 0usize;
+        for i in 0..count {
+            let end = read_u32(4 + i * 4)? as usize;
+            let s = data
+                .get(start..end)
+                .ok_or(BinaryError::BinaryTooShort.throw())?;
+            let s = str::from_utf8(s).map_err(|_| BinaryError::WrongFileFormat.throw())?;
+            // a duplicate would shift the symbol of every later string
+            if !table.strings.insert(FastStr::new(s)) {
+                return Err(BinaryError::WrongFileFormat.throw());
+            }
+            start = end;
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StringName;
+
+    #[test]
+    fn test_intern() {
+        let a = StringName::intern("System.UInt64");
+        let b = StringName::intern(String::from("System.UInt64"));
+        assert_eq!(a.symbol(), b.symbol());
+        assert_eq!(a, b);
+        assert_eq!(a, StringName::from("System.UInt64"));
+        assert!(a == *"System.UInt64");
+        assert_eq!(
+            SymbolTable::global().resolve(a.symbol().unwrap()),
+            Some("System.UInt64")
+        );
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let mut table = SymbolTable::new();
+        let a = table.intern("a");
+        let empty = table.intern("");
+        let long = table.intern("System.Collections.Generic.List");
+        assert_eq!(table.intern("a"), a);
+        let decoded = SymbolTable::decode(&table.encode()).unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded.resolve(empty), Some(""));
+        assert_eq!(
+            decoded.resolve(long),
+            Some("System.Collections.Generic.List")
+        );
+        assert!(SymbolTable::decode(&table.encode()[..6]).is_err());
+
+        let duplicate = [2u32, 1, 2]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .chain(*b"aa")
+            .collect::<Vec<_>>();
+        assert!(SymbolTable::decode(&duplicate).is_err());
+    }
+}